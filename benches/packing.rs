@@ -0,0 +1,131 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use corral::image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use corral::{AtlasBuilder, DataSize, Packer, Tree2d};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+/// Sprite sizes cycling through a spread typical of game art
+fn sprite(i: u32) -> DynamicImage {
+    let size = [8, 16, 24, 32, 48, 64][i as usize % 6];
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(size, size / 2 + 4, |x, y| {
+        Rgba([(x * 4) as u8, (y * 4) as u8, i as u8, 255])
+    }))
+}
+
+/// A directory of `count` sprites, reused by later runs once every file is there
+fn sprite_dir(count: u32) -> PathBuf {
+    let temp_dir = std::env::temp_dir();
+    let dir = temp_dir.join(format!("corral-bench-{count}"));
+    let complete = fs::read_dir(&dir)
+        .map(|entries| entries.count() == count as usize)
+        .unwrap_or(false);
+    if !complete {
+        // Written elsewhere and moved into place, so an interrupted run can't leave some of
+        // the sprites behind to be benchmarked next time
+        let partial = temp_dir.join(format!("corral-bench-{count}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&partial);
+        fs::create_dir_all(&partial).unwrap();
+        for i in 0..count {
+            sprite(i)
+                .save(partial.join(format!("sprite_{i}.png")))
+                .unwrap();
+        }
+        let _ = fs::remove_dir_all(&dir);
+        fs::rename(&partial, &dir).unwrap();
+    }
+    dir
+}
+
+/// The baseline, decoding one file after another
+fn decode_sequentially(dir: &Path) -> Vec<DynamicImage> {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| corral::image::open(entry.unwrap().path()).unwrap())
+        .collect()
+}
+
+/// The baseline, packing as `AtlasBuilder` does but copying every pixel, padding included,
+/// on its own
+fn pack_per_pixel(images: &[DynamicImage], padding: u32) -> RgbaImage {
+    let mut sizes: Vec<(DataSize, &DynamicImage)> = images
+        .iter()
+        .map(|img| {
+            let width = img.width() + padding * 2;
+            let height = img.height() + padding * 2;
+            (DataSize { width, height }, img)
+        })
+        .collect();
+    sizes.sort_by_key(|(size, _)| std::cmp::Reverse(size.width * size.height));
+    let mut tree = Tree2d::new();
+    tree.insert_all(sizes).unwrap();
+
+    let bounds = tree.bounds();
+    let mut sheet = RgbaImage::new(bounds.width + padding, bounds.height + padding);
+    for (img, bb) in tree.flatten() {
+        let (width, height) = (img.width() as i64, img.height() as i64);
+        let padding = padding as i64;
+        for y in -padding..height + padding {
+            for x in -padding..width + padding {
+                let p = img.get_pixel(x.clamp(0, width - 1) as u32, y.clamp(0, height - 1) as u32);
+                let tx = (bb.x as i64 + padding + x) as u32;
+                let ty = (bb.y as i64 + padding + y) as u32;
+                sheet.put_pixel(tx, ty, p);
+            }
+        }
+    }
+    sheet
+}
+
+fn decode(c: &mut Criterion) {
+    let dir = sprite_dir(500);
+    let mut group = c.benchmark_group("decode 500 pngs");
+    group.bench_function("sequential", |b| b.iter(|| decode_sequentially(&dir)));
+    let dir = dir.to_str().unwrap();
+    group.bench_function("parallel", |b| {
+        b.iter(|| AtlasBuilder::new().add_dir(dir).unwrap())
+    });
+    group.finish();
+}
+
+fn blit(c: &mut Criterion) {
+    let images: Vec<DynamicImage> = (0..500).map(sprite).collect();
+    let mut group = c.benchmark_group("pack 500 sprites");
+    group.bench_function("per pixel", |b| b.iter(|| pack_per_pixel(&images, 2)));
+    group.bench_function("row at a time", |b| {
+        b.iter_batched(
+            || {
+                images
+                    .iter()
+                    .enumerate()
+                    .fold(AtlasBuilder::new(), |builder, (i, img)| {
+                        builder.add_image(&i.to_string(), img.clone())
+                    })
+            },
+            |builder| builder.build().unwrap(),
+            criterion::BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn insert(c: &mut Criterion) {
+    let mut sizes: Vec<(DataSize, u32)> = (0..20_000)
+        .map(|i| {
+            let width = [8, 16, 24, 32, 48, 64][i as usize % 6];
+            let height = [4, 12, 20, 36][i as usize % 4];
+            (DataSize { width, height }, i)
+        })
+        .collect();
+    sizes.sort_by_key(|(size, _)| std::cmp::Reverse(size.width * size.height));
+    c.bench_function("insert 20000 rectangles", |b| {
+        b.iter_batched(
+            || sizes.clone(),
+            |sizes| Tree2d::new().insert_all(sizes).unwrap(),
+            criterion::BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, decode, blit, insert);
+criterion_main!(benches);
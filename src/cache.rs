@@ -0,0 +1,197 @@
+//! The previous packing, kept next to the sheet so the next run can reuse it
+//!
+//! Holds a hash of every input file, where each sprite went and the options that decide the
+//! layout. When a run finds only pixels have changed, the changed sprites are redrawn in place
+//! and every coordinate stays as it was.
+
+use std::collections::HashMap;
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::CorralError;
+use crate::meta_data::{self, SheetData, SpriteData};
+use crate::nine_patch::NinePatch;
+
+/// Bumped whenever the layout of the cache file changes, older caches are then ignored
+const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Cache {
+    version: u32,
+    pub padding: u8,
+    /// Of the sheet's png file, to notice it being changed or replaced since
+    pub sheet_hash: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Of the sheet's pixels, as written to metadata
+    pub image_hash: u64,
+    pub sprites: Vec<CachedSprite>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CachedSprite {
+    pub path: String,
+    /// Of the input file's contents
+    pub hash: u64,
+    pub name: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub nine_patch: Option<NinePatch>,
+}
+
+impl CachedSprite {
+    pub fn to_sprite_data(&self) -> SpriteData {
+        SpriteData {
+            name: self.name.clone(),
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+            nine_patch: self.nine_patch,
+        }
+    }
+}
+
+/// Hash of an input or output file's contents
+pub fn hash(contents: &[u8]) -> u64 {
+    meta_data::fnv1a(meta_data::FNV_OFFSET_BASIS, contents)
+}
+
+impl Cache {
+    /// Where the cache for a sheet is kept
+    pub fn path(output_file: &str) -> String {
+        format!("{output_file}.cache")
+    }
+
+    /// `inputs` are each file's path, the name of the sprite it became and its hash
+    ///
+    /// `None` when two files became sprites of the same name, as they can't be told apart.
+    pub fn new(
+        sheet_data: &SheetData,
+        sheet_hash: u64,
+        inputs: &[(&str, &str, u64)],
+    ) -> Option<Self> {
+        let by_name: HashMap<&str, &SpriteData> = sheet_data
+            .sprites
+            .iter()
+            .map(|sd| (sd.name.as_str(), sd))
+            .collect();
+        if by_name.len() != sheet_data.sprites.len() || by_name.len() != inputs.len() {
+            return None;
+        }
+        let sprites = inputs
+            .iter()
+            .map(|(path, name, hash)| {
+                let sd = by_name.get(name)?;
+                Some(CachedSprite {
+                    path: (*path).to_owned(),
+                    hash: *hash,
+                    name: sd.name.clone(),
+                    x: sd.x,
+                    y: sd.y,
+                    width: sd.width,
+                    height: sd.height,
+                    nine_patch: sd.nine_patch,
+                })
+            })
+            .collect::<Option<_>>()?;
+        Some(Cache {
+            version: VERSION,
+            padding: sheet_data.padding,
+            sheet_hash,
+            width: sheet_data.width,
+            height: sheet_data.height,
+            image_hash: sheet_data.image_hash,
+            sprites,
+        })
+    }
+
+    /// `None` when there is no cache, or it can't be read, either way meaning a full packing
+    pub fn load(path: &str) -> Option<Self> {
+        let contents = fs::read(path).ok()?;
+        let cache: Cache = serde_json::from_slice(&contents).ok()?;
+        (cache.version == VERSION).then_some(cache)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), CorralError> {
+        serde_json::to_vec(self)
+            .map_err(io::Error::other)
+            .and_then(|contents| fs::write(path, contents))
+            .map_err(|err| CorralError::io(path, err))
+    }
+
+    pub fn sprites_by_path(&self) -> HashMap<&str, &CachedSprite> {
+        self.sprites
+            .iter()
+            .map(|sprite| (sprite.path.as_str(), sprite))
+            .collect()
+    }
+
+    pub fn to_sheet_data(&self, output_file: &str) -> SheetData {
+        SheetData::new(
+            output_file,
+            self.width,
+            self.height,
+            self.padding,
+            self.image_hash,
+            self.sprites
+                .iter()
+                .map(CachedSprite::to_sprite_data)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load() -> Result<(), CorralError> {
+        let sheet_data = SheetData::new(
+            "out/sheet.png",
+            16,
+            8,
+            1,
+            42,
+            vec![SpriteData {
+                name: "in/a".to_owned(),
+                x: 1,
+                y: 1,
+                width: 4,
+                height: 6,
+                nine_patch: None,
+            }],
+        );
+        let cache = Cache::new(&sheet_data, 7, &[("in/a.png", "in/a", 99)]).unwrap();
+        assert!(Cache::new(
+            &sheet_data,
+            7,
+            &[("in/a.png", "in/a", 99), ("in/a.jpg", "in/a", 98)]
+        )
+        .is_none());
+        let path = std::env::temp_dir().join("corral-save-and-load.png.cache");
+        let path = path.to_str().unwrap();
+        cache.save(path)?;
+
+        let loaded = Cache::load(path).expect("cache should load");
+        assert_eq!((7, 42), (loaded.sheet_hash, loaded.image_hash));
+        assert_eq!((16, 8, 1), (loaded.width, loaded.height, loaded.padding));
+        let sprites = loaded.sprites_by_path();
+        let sprite = sprites.get("in/a.png").expect("sprite should be cached");
+        assert_eq!((99, 1, 4), (sprite.hash, sprite.x, sprite.width));
+        assert!(!sprites.contains_key("in/b.png"));
+        Ok(())
+    }
+
+    #[test]
+    fn unreadable_cache() {
+        let path = std::env::temp_dir().join("corral-unreadable.png.cache");
+        fs::write(&path, "not a cache").unwrap();
+        assert!(Cache::load(path.to_str().unwrap()).is_none());
+        assert!(Cache::load("does/not/exist.cache").is_none());
+    }
+}
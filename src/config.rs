@@ -1,16 +1,154 @@
-use std::{fmt, process::exit};
+use std::fmt;
+use std::path::Path;
 
-#[derive(Clone, Copy)]
+use crate::cache::Cache;
+use crate::error::CorralError;
+use crate::meta_data::{self, TemplateFile};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MetaDataFormat {
     Json,
     Lua,
+    TexturePackerHash,
+    TexturePackerArray,
+    LibGdx,
+    Starling,
+    Godot,
+    Bevy,
+    Unity,
+    Cocos2d,
+    Css,
+    Scss,
+    Rust,
+    CHeader,
+    Template,
+    Binary,
+    MsgPack,
+    Cbor,
+}
+
+impl MetaDataFormat {
+    const NAMES: [(&'static str, MetaDataFormat); 18] = [
+        ("json", MetaDataFormat::Json),
+        ("lua", MetaDataFormat::Lua),
+        ("texturepacker-hash", MetaDataFormat::TexturePackerHash),
+        ("texturepacker-array", MetaDataFormat::TexturePackerArray),
+        ("libgdx", MetaDataFormat::LibGdx),
+        ("starling", MetaDataFormat::Starling),
+        ("godot", MetaDataFormat::Godot),
+        ("bevy", MetaDataFormat::Bevy),
+        ("unity", MetaDataFormat::Unity),
+        ("cocos2d", MetaDataFormat::Cocos2d),
+        ("css", MetaDataFormat::Css),
+        ("scss", MetaDataFormat::Scss),
+        ("rust", MetaDataFormat::Rust),
+        ("c", MetaDataFormat::CHeader),
+        ("template", MetaDataFormat::Template),
+        ("binary", MetaDataFormat::Binary),
+        ("msgpack", MetaDataFormat::MsgPack),
+        ("cbor", MetaDataFormat::Cbor),
+    ];
+
+    /// As given to `--data-fmt`
+    pub fn name(self) -> &'static str {
+        MetaDataFormat::NAMES
+            .iter()
+            .find(|(_, format)| *format == self)
+            .map(|(name, _)| *name)
+            .unwrap_or_default()
+    }
+
+    fn from_name(name: &str) -> Option<MetaDataFormat> {
+        MetaDataFormat::NAMES
+            .iter()
+            .find(|(format_name, _)| *format_name == name)
+            .map(|(_, format)| *format)
+    }
+}
+
+/// How far sprite UVs reach towards the sprite's edges
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UvMode {
+    /// On the sprite's outer edges
+    Exact,
+    /// Half a texel inside the sprite's outer edges
+    Inset,
+}
+
+/// Corner of the sheet that UVs are measured from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UvOrigin {
+    /// DirectX, Vulkan and Metal
+    #[default]
+    TopLeft,
+    /// OpenGL, where v is flipped
+    BottomLeft,
+}
+
+/// A metadata format to write, by default next to the sheet image
+#[derive(Clone)]
+pub struct MetaDataOutput {
+    pub format: MetaDataFormat,
+    pub path: Option<String>,
+}
+
+impl MetaDataOutput {
+    /// Parses `format[:path]`, separated by commas
+    fn parse_list(value: &str) -> Result<Vec<MetaDataOutput>, CorralError> {
+        value
+            .split(',')
+            .map(|output| {
+                let (name, path) = match output.split_once(':') {
+                    Some((name, path)) => (name, Some(path.to_owned())),
+                    None => (output, None),
+                };
+                match MetaDataFormat::from_name(name) {
+                    Some(format) => Ok(MetaDataOutput { format, path }),
+                    None => Err(CorralError::Config {
+                        message: format!("Unrecognised data format `{name}`"),
+                    }),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Settings that only some metadata formats make use of
+#[derive(Clone, Default)]
+pub struct MetaDataOptions {
+    /// Prepended to the identifiers of generated C headers
+    pub prefix: Option<String>,
+    /// Handlebars template rendered by the template format
+    pub template: Option<TemplateFile>,
+    /// Writes json as the bare sprite array, without the `meta` header
+    pub json_array: bool,
+    /// Adds u0, v0, u1, v1 to json and lua sprites
+    pub uv: Option<UvMode>,
+    pub uv_origin: UvOrigin,
+    /// Keeps sprite names' case for lua keys, rather than upper casing them
+    pub keep_case: bool,
 }
 
+#[derive(Clone)]
 pub struct Config {
     pub padding: u8,
     pub input_dir: String,
     pub output_file: String,
-    pub output_file_format: MetaDataFormat,
+    pub meta_data_outputs: Vec<MetaDataOutput>,
+    pub meta_data_options: MetaDataOptions,
+    /// Reuses the previous packing, kept next to the sheet, when only sprites' pixels changed
+    pub cache: bool,
+    /// Keeps sprites where the previous packing put them, placing new ones around them
+    pub stable: bool,
+    /// Packs again whenever the input directory changes, until killed
+    pub watch: bool,
+}
+
+/// What the command line asks corral to do
+pub enum Command {
+    Run(Config),
+    /// `--help`, holding the usage text
+    Help(String),
 }
 
 struct NamedArg<'a> {
@@ -36,13 +174,24 @@ impl fmt::Display for NamedArg<'_> {
     }
 }
 
+enum ParamValues<'a> {
+    /// `--name`
+    Flag,
+    /// `--name=value`, where value is one of these
+    OneOf(&'a [&'a str]),
+    /// `--name=value`, where value is anything, shown in the usage as this placeholder
+    Any(&'a str),
+    /// `--name=value[:path],...`, where each value is one of these
+    ListOf(&'a [&'a str]),
+}
+
 struct NamedParam<'a> {
     name: &'a str,
-    valid_values: Option<&'a [&'a str]>,
+    valid_values: ParamValues<'a>,
 }
 
 impl<'a> NamedParam<'a> {
-    fn parse(&self, args: &[String]) -> Result<Option<NamedArg>, &'a str> {
+    fn parse<'b>(&'b self, args: &'b [String]) -> Result<Option<NamedArg<'b>>, CorralError> {
         for arg in args {
             if let Some(index) = arg.find(&format!("--{name}", name = self.name)) {
                 if index != 0 {
@@ -56,10 +205,15 @@ impl<'a> NamedParam<'a> {
                     }));
                 }
                 if invoked_arg.len() != 2 {
-                    return Err("incorrect format");
+                    return Err(CorralError::Config {
+                        message: format!(
+                            "{arg} has more than one `=`, --{name} takes a single value",
+                            name = self.name
+                        ),
+                    });
                 }
                 match self.valid_values {
-                    Some(valid_values) => {
+                    ParamValues::OneOf(valid_values) => {
                         for value in valid_values {
                             if invoked_arg[1] == *value {
                                 return Ok(Some(NamedArg {
@@ -68,8 +222,21 @@ impl<'a> NamedParam<'a> {
                                 }));
                             }
                         }
+                        return Err(CorralError::Config {
+                            message: format!(
+                                "{arg} isn't valid, --{name} takes one of {values}",
+                                name = self.name,
+                                values = valid_values.join("|")
+                            ),
+                        });
+                    }
+                    ParamValues::Any(_) | ParamValues::ListOf(_) => {
+                        return Ok(Some(NamedArg {
+                            name: self.name,
+                            value: Some(invoked_arg[1]),
+                        }));
                     }
-                    None => {
+                    ParamValues::Flag => {
                         return Ok(Some(NamedArg {
                             name: self.name,
                             value: None,
@@ -85,7 +252,7 @@ impl<'a> NamedParam<'a> {
 impl fmt::Display for NamedParam<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         match self.valid_values {
-            Some(valid_values) => {
+            ParamValues::OneOf(valid_values) => {
                 write!(
                     f,
                     "[--{name}={values}]",
@@ -93,65 +260,481 @@ impl fmt::Display for NamedParam<'_> {
                     values = valid_values.join("|")
                 )
             }
-            None => {
+            ParamValues::ListOf(valid_values) => {
+                write!(
+                    f,
+                    "[--{name}={values}[:path][,...]]",
+                    name = self.name,
+                    values = valid_values.join("|")
+                )
+            }
+            ParamValues::Any(placeholder) => {
+                write!(f, "[--{name}={placeholder}]", name = self.name)
+            }
+            ParamValues::Flag => {
                 write!(f, "[--{name}]", name = self.name,)
             }
         }
     }
 }
 
-impl Config {
-    pub fn parse(args: &[String]) -> Result<Config, &'static str> {
+impl Command {
+    pub fn parse(args: &[String]) -> Result<Command, CorralError> {
+        let format_names: Vec<&str> = MetaDataFormat::NAMES
+            .iter()
+            .map(|(name, _)| *name)
+            .collect();
         let named_params = [
             NamedParam {
                 name: "data-fmt",
-                valid_values: Some(&["json", "lua"]),
+                valid_values: ParamValues::ListOf(&format_names),
+            },
+            NamedParam {
+                name: "template",
+                valid_values: ParamValues::Any("template_file"),
+            },
+            NamedParam {
+                name: "prefix",
+                valid_values: ParamValues::Any("identifier_prefix"),
+            },
+            NamedParam {
+                name: "json-array",
+                valid_values: ParamValues::Flag,
+            },
+            NamedParam {
+                name: "uv",
+                valid_values: ParamValues::OneOf(&["exact", "inset"]),
+            },
+            NamedParam {
+                name: "uv-origin",
+                valid_values: ParamValues::OneOf(&["top-left", "bottom-left"]),
+            },
+            NamedParam {
+                name: "keep-case",
+                valid_values: ParamValues::Flag,
+            },
+            NamedParam {
+                name: "no-cache",
+                valid_values: ParamValues::Flag,
+            },
+            NamedParam {
+                name: "stable",
+                valid_values: ParamValues::Flag,
+            },
+            NamedParam {
+                name: "watch",
+                valid_values: ParamValues::Flag,
             },
             NamedParam {
                 name: "help",
-                valid_values: None,
+                valid_values: ParamValues::Flag,
             }, // NamedParam {name: "padding"}
         ];
 
-        let mut metadata_format = MetaDataFormat::Json;
+        let mut meta_data_outputs = vec![MetaDataOutput {
+            format: MetaDataFormat::Json,
+            path: None,
+        }];
+        let mut meta_data_options = MetaDataOptions::default();
+        let mut cache = true;
+        let mut stable = false;
+        let mut watch = false;
 
         for named_param in &named_params {
-            if let Ok(Some(arg)) = named_param.parse(args) {
+            if let Some(arg) = named_param.parse(args)? {
                 match arg {
                     NamedArg {
                         name: "data-fmt",
-                        value: Some("json"),
-                    } => metadata_format = MetaDataFormat::Json,
+                        value: Some(value),
+                    } => meta_data_outputs = MetaDataOutput::parse_list(value)?,
                     NamedArg {
-                        name: "data-fmt",
-                        value: Some("lua"),
-                    } => metadata_format = MetaDataFormat::Lua,
+                        name: "template",
+                        value: Some(template),
+                    } => meta_data_options.template = Some(TemplateFile::load(template)?),
+                    NamedArg {
+                        name: "prefix",
+                        value: Some(prefix),
+                    } => meta_data_options.prefix = Some(prefix.to_owned()),
+                    NamedArg {
+                        name: "json-array",
+                        value: None,
+                    } => meta_data_options.json_array = true,
+                    NamedArg {
+                        name: "uv",
+                        value: Some(mode),
+                    } => {
+                        meta_data_options.uv = match mode {
+                            "inset" => Some(UvMode::Inset),
+                            _ => Some(UvMode::Exact),
+                        }
+                    }
+                    NamedArg {
+                        name: "uv-origin",
+                        value: Some(origin),
+                    } => {
+                        meta_data_options.uv_origin = match origin {
+                            "bottom-left" => UvOrigin::BottomLeft,
+                            _ => UvOrigin::TopLeft,
+                        }
+                    }
+                    NamedArg {
+                        name: "keep-case",
+                        value: None,
+                    } => meta_data_options.keep_case = true,
+                    NamedArg {
+                        name: "no-cache",
+                        value: None,
+                    } => cache = false,
+                    NamedArg {
+                        name: "stable",
+                        value: None,
+                    } => stable = true,
+                    NamedArg {
+                        name: "watch",
+                        value: None,
+                    } => watch = true,
                     NamedArg {
                         name: "help",
                         value: None,
                     } => {
-                        println!("A simple sprite sheet packer\n\nUsage: `corral input_dir output_sheet.png [--data-fmt=json|lua]");
-                        exit(0);
+                        return Ok(Command::Help(format!(
+                            "A simple sprite sheet packer\n\nUsage: `corral input_dir output_sheet.png {params}`",
+                            params = named_params
+                                .iter()
+                                .map(|param| param.to_string())
+                                .collect::<Vec<String>>()
+                                .join(" ")
+                        )));
                     }
-                    _ => {
-                        return Err("Unrecognised argument");
+                    arg => {
+                        return Err(CorralError::Config {
+                            message: format!("Unrecognised argument, {arg}"),
+                        });
                     }
                 }
             };
         }
 
+        let uses_template = meta_data_outputs
+            .iter()
+            .any(|output| matches!(output.format, MetaDataFormat::Template));
+        if uses_template && meta_data_options.template.is_none() {
+            return Err(CorralError::config(
+                "--data-fmt=template needs a template file, --template=template_file",
+            ));
+        }
+
+        // The prefix starts every identifier in the C header, so has to be the start of one
+        if let Some(prefix) = &meta_data_options.prefix {
+            let valid = prefix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !prefix.starts_with(|c: char| c.is_ascii_digit());
+            if !valid {
+                return Err(CorralError::Config {
+                    message: format!(
+                        "--prefix={prefix} can't start a C identifier, use only letters, digits and `_`, not starting with a digit"
+                    ),
+                });
+            }
+        }
+
+        if stable && !cache {
+            return Err(CorralError::config(
+                "--stable keeps positions from the cache, so can't be used with --no-cache",
+            ));
+        }
+
         if args.len() < 3 {
-            return Err("Too few arguments, Usage: `corral input_dir output_sheet.png [--data-fmt=json|lua]`");
+            return Err(CorralError::config("Too few arguments, Usage: `corral input_dir output_sheet.png [options]`, see `corral --help`"));
         }
 
         let input_dir = args[1].clone();
         let output_file = args[2].clone();
 
-        Ok(Config {
+        // Formats sharing an extension, such as json and the TexturePacker formats, would
+        // otherwise overwrite each other, and a path given with `--data-fmt` could overwrite
+        // the sheet, its cache or Godot's `AtlasTexture`s
+        let mut paths: Vec<(String, String)> = vec![(output_file.clone(), "the sheet".to_owned())];
+        if cache {
+            paths.push((Cache::path(&output_file), "the cache".to_owned()));
+        }
+        let mut dirs: Vec<(String, String)> = vec![];
+        for output in &meta_data_outputs {
+            let format = output.format.name();
+            let collision = |other: &str, path: &str| {
+                CorralError::Config {
+                message: format!(
+                    "{other} and {format} would both be written to {path}, give one of them its own path, such as --data-fmt={format}:other_path"
+                ),
+            }
+            };
+            let path = meta_data::output_path(&output_file, output, &meta_data_options);
+            if let Some((_, other)) = paths.iter().find(|(other_path, _)| *other_path == path) {
+                return Err(collision(other, &path));
+            }
+            if let Some((_, other)) = dirs
+                .iter()
+                .find(|(dir, _)| Path::new(&path).starts_with(dir))
+            {
+                return Err(collision(&format!("{other}'s resources"), &path));
+            }
+            if let Some(dir) = meta_data::output_dir(&output_file, output, &meta_data_options) {
+                if let Some((other_path, other)) = paths
+                    .iter()
+                    .find(|(other_path, _)| Path::new(other_path).starts_with(&dir))
+                {
+                    return Err(CorralError::Config {
+                        message: format!(
+                            "{other} and {format}'s resources would both be written to {other_path}, give one of them its own path, such as --data-fmt={format}:other_path"
+                        ),
+                    });
+                }
+                dirs.push((dir, format.to_owned()));
+            }
+            paths.push((path, format.to_owned()));
+        }
+
+        Ok(Command::Run(Config {
             padding: 2u8,
             input_dir,
             output_file,
-            output_file_format: metadata_format,
-        })
+            meta_data_outputs,
+            meta_data_options,
+            cache,
+            stable,
+            watch,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(extra: &[&str]) -> Vec<String> {
+        ["corral", "input_dir", "output.png"]
+            .iter()
+            .chain(extra)
+            .map(|arg| arg.to_string())
+            .collect()
+    }
+
+    fn parse(args: &[String]) -> Result<Config, CorralError> {
+        match Command::parse(args)? {
+            Command::Run(config) => Ok(config),
+            Command::Help(_) => panic!("only --help should give the usage"),
+        }
+    }
+
+    #[test]
+    fn default_data_fmt() -> Result<(), CorralError> {
+        let config = parse(&args(&[]))?;
+        assert_eq!(1, config.meta_data_outputs.len());
+        assert!(matches!(
+            config.meta_data_outputs[0].format,
+            MetaDataFormat::Json
+        ));
+        assert_eq!(None, config.meta_data_outputs[0].path);
+        assert_eq!("input_dir", config.input_dir);
+        assert_eq!("output.png", config.output_file);
+        Ok(())
+    }
+
+    #[test]
+    fn data_fmt() -> Result<(), CorralError> {
+        let config = parse(&args(&["--data-fmt=godot"]))?;
+        assert!(matches!(
+            config.meta_data_outputs[0].format,
+            MetaDataFormat::Godot
+        ));
+        let config = parse(&args(&["--data-fmt=starling"]))?;
+        assert!(matches!(
+            config.meta_data_outputs[0].format,
+            MetaDataFormat::Starling
+        ));
+        let config = parse(&args(&["--data-fmt=libgdx"]))?;
+        assert!(matches!(
+            config.meta_data_outputs[0].format,
+            MetaDataFormat::LibGdx
+        ));
+        assert!(parse(&args(&["--data-fmt=yaml"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn data_fmt_list() -> Result<(), CorralError> {
+        let config = parse(&args(&["--data-fmt=json,lua:game/sprites.lua"]))?;
+        assert_eq!(2, config.meta_data_outputs.len());
+        assert!(matches!(
+            config.meta_data_outputs[0].format,
+            MetaDataFormat::Json
+        ));
+        assert_eq!(None, config.meta_data_outputs[0].path);
+        assert!(matches!(
+            config.meta_data_outputs[1].format,
+            MetaDataFormat::Lua
+        ));
+        assert_eq!(
+            Some("game/sprites.lua".to_owned()),
+            config.meta_data_outputs[1].path
+        );
+        assert!(parse(&args(&["--data-fmt=json,,lua"])).is_err());
+        assert!(matches!(
+            parse(&args(&["--data-fmt=json:a=b.json"])),
+            Err(CorralError::Config { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn data_fmt_same_path() -> Result<(), CorralError> {
+        for same_path in [
+            "--data-fmt=json,texturepacker-hash",
+            "--data-fmt=texturepacker-hash,texturepacker-array",
+            "--data-fmt=json:output.lua,lua",
+            "--data-fmt=lua,lua",
+            "--data-fmt=json:output.png",
+            "--data-fmt=json:output.png.cache",
+            "--data-fmt=unity:output.png.cache",
+            "--data-fmt=godot,json:output/a.json",
+            "--data-fmt=lua:output/game/a.lua,godot",
+            "--data-fmt=godot,godot:output",
+        ] {
+            assert!(
+                matches!(parse(&args(&[same_path])), Err(CorralError::Config { .. })),
+                "{same_path}"
+            );
+        }
+        let config = parse(&args(&[
+            "--data-fmt=json,texturepacker-hash:out.hash.json,texturepacker-array:out.array.json",
+        ]))?;
+        assert_eq!(3, config.meta_data_outputs.len());
+        assert!(parse(&args(&["--data-fmt=json,unity,lua"])).is_ok());
+        assert!(parse(&args(&["--data-fmt=json:output.png.cache", "--no-cache"])).is_ok());
+        assert!(parse(&args(&["--data-fmt=godot,json:output.godot/a.json"])).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn prefix() -> Result<(), CorralError> {
+        let config = parse(&args(&[]))?;
+        assert_eq!(None, config.meta_data_options.prefix);
+        let config = parse(&args(&["--data-fmt=c", "--prefix=game_"]))?;
+        assert!(matches!(
+            config.meta_data_outputs[0].format,
+            MetaDataFormat::CHeader
+        ));
+        assert_eq!(Some("game_".to_owned()), config.meta_data_options.prefix);
+        for invalid in [
+            "--prefix=my-game_",
+            "--prefix=2d_",
+            "--prefix=game.",
+            "--prefix=é_",
+        ] {
+            assert!(
+                parse(&args(&["--data-fmt=c", invalid])).is_err(),
+                "{invalid}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn template() -> Result<(), CorralError> {
+        assert!(parse(&args(&["--data-fmt=json,template"])).is_err());
+        let path = std::env::temp_dir().join("corral-config-template.lua.hbs");
+        std::fs::write(&path, "{{#each sprites}}{{name}}{{/each}}").unwrap();
+        let template_arg = format!("--template={}", path.display());
+        let config = parse(&args(&["--data-fmt=template", &template_arg]))?;
+        assert!(matches!(
+            config.meta_data_outputs[0].format,
+            MetaDataFormat::Template
+        ));
+        assert_eq!(
+            Some(path.to_str().unwrap()),
+            config
+                .meta_data_options
+                .template
+                .as_ref()
+                .map(|template| template.path.as_str())
+        );
+        assert!(parse(&args(&["--data-fmt=template", "--template=missing.hbs"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn json_array() -> Result<(), CorralError> {
+        assert!(!parse(&args(&[]))?.meta_data_options.json_array);
+        assert!(
+            parse(&args(&["--json-array"]))?
+                .meta_data_options
+                .json_array
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn uv() -> Result<(), CorralError> {
+        let config = parse(&args(&[]))?;
+        assert_eq!(None, config.meta_data_options.uv);
+        assert_eq!(UvOrigin::TopLeft, config.meta_data_options.uv_origin);
+        let config = parse(&args(&["--uv=inset", "--uv-origin=bottom-left"]))?;
+        assert_eq!(Some(UvMode::Inset), config.meta_data_options.uv);
+        assert_eq!(UvOrigin::BottomLeft, config.meta_data_options.uv_origin);
+        assert!(matches!(
+            parse(&args(&["--uv=outset"])),
+            Err(CorralError::Config { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn keep_case() -> Result<(), CorralError> {
+        assert!(!parse(&args(&[]))?.meta_data_options.keep_case);
+        assert!(parse(&args(&["--keep-case"]))?.meta_data_options.keep_case);
+        Ok(())
+    }
+
+    #[test]
+    fn no_cache() -> Result<(), CorralError> {
+        assert!(parse(&args(&[]))?.cache);
+        assert!(!parse(&args(&["--no-cache"]))?.cache);
+        Ok(())
+    }
+
+    #[test]
+    fn stable() -> Result<(), CorralError> {
+        assert!(!parse(&args(&[]))?.stable);
+        assert!(parse(&args(&["--stable"]))?.stable);
+        assert!(matches!(
+            parse(&args(&["--stable", "--no-cache"])),
+            Err(CorralError::Config { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn watch() -> Result<(), CorralError> {
+        assert!(!parse(&args(&[]))?.watch);
+        assert!(parse(&args(&["--watch"]))?.watch);
+        Ok(())
+    }
+
+    #[test]
+    fn too_few_arguments() {
+        assert!(matches!(
+            parse(&["corral".to_owned()]),
+            Err(CorralError::Config { .. })
+        ));
+    }
+
+    #[test]
+    fn help() -> Result<(), CorralError> {
+        match Command::parse(&args(&["--help"]))? {
+            Command::Help(usage) => assert!(usage.contains("[--data-fmt=json|")),
+            Command::Run(_) => panic!("--help should give the usage"),
+        }
+        Ok(())
     }
 }
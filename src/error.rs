@@ -0,0 +1,111 @@
+use std::{error::Error, fmt, io};
+
+use crate::tree2d::PackError;
+
+/// Everything that can stop corral, each with what it was working on at the time
+#[derive(Debug)]
+pub enum CorralError {
+    /// The arguments don't make a valid configuration
+    Config { message: String },
+    /// A file or directory couldn't be read or written
+    Io { path: String, source: io::Error },
+    /// An input image couldn't be decoded, or its nine-patch markers couldn't be read
+    Decode { path: String, message: String },
+    /// A sprite didn't fit in the sheet
+    Pack { sprite: String, source: PackError },
+    /// A metadata format couldn't be generated
+    MetaData {
+        format: &'static str,
+        message: String,
+    },
+}
+
+impl CorralError {
+    /// Distinct for each kind of error, so scripts can tell them apart
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CorralError::Config { .. } => 2,
+            CorralError::Io { .. } => 3,
+            CorralError::Decode { .. } => 4,
+            CorralError::Pack { .. } => 5,
+            CorralError::MetaData { .. } => 6,
+        }
+    }
+
+    pub fn config(message: &str) -> Self {
+        CorralError::Config {
+            message: message.to_owned(),
+        }
+    }
+
+    pub fn io(path: &str, source: io::Error) -> Self {
+        CorralError::Io {
+            path: path.to_owned(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for CorralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            CorralError::Config { message } => write!(f, "Problem parsing arguments: {message}"),
+            CorralError::Io { path, source } => write!(f, "Error accessing {path}: {source}"),
+            CorralError::Decode { path, message } => write!(f, "Error decoding {path}: {message}"),
+            CorralError::Pack { sprite, source } => write!(f, "Error packing {sprite}: {source}"),
+            CorralError::MetaData { format, message } => {
+                write!(f, "Error writing {format} data: {message}")
+            }
+        }
+    }
+}
+
+impl Error for CorralError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CorralError::Io { source, .. } => Some(source),
+            CorralError::Pack { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distinct_exit_codes() {
+        let errors = [
+            CorralError::config("bad"),
+            CorralError::io("a.png", io::Error::from(io::ErrorKind::NotFound)),
+            CorralError::Decode {
+                path: "a.png".to_owned(),
+                message: "bad".to_owned(),
+            },
+            CorralError::Pack {
+                sprite: "a".to_owned(),
+                source: PackError::NoSpace {
+                    width: 1,
+                    height: 1,
+                },
+            },
+            CorralError::MetaData {
+                format: "json",
+                message: "bad".to_owned(),
+            },
+        ];
+        let mut codes: Vec<i32> = errors.iter().map(|err| err.exit_code()).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(errors.len(), codes.len());
+        assert!(!codes.contains(&0));
+    }
+
+    #[test]
+    fn context_in_message() {
+        let err = CorralError::io("in/a.png", io::Error::from(io::ErrorKind::NotFound));
+        assert!(err.to_string().contains("in/a.png"));
+        assert!(err.source().is_some());
+    }
+}
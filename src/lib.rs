@@ -0,0 +1,53 @@
+//! Packs images into a sprite sheet and describes where each one went
+//!
+//! [`AtlasBuilder`] packs images already in memory, [`run`] does what the `corral` command
+//! does, reading a directory and writing the sheet and its metadata. [`Packer`] packs plain
+//! rectangles, for anything else that needs laying out.
+
+// Its tests spell out the operators they exercise, `&a + &b` and `a < b` against a bool
+#[cfg_attr(test, allow(clippy::op_ref, clippy::bool_assert_comparison))]
+mod bounding_box;
+mod cache;
+mod config;
+mod error;
+mod meta_data;
+mod nine_patch;
+mod packer;
+mod tree2d;
+mod watch;
+
+pub use bounding_box::BoundingBox;
+pub use error::CorralError;
+pub use image;
+pub use meta_data::binary::{BinaryAnimation, BinaryAtlas, BinarySprite};
+pub use meta_data::SpriteData;
+pub use nine_patch::NinePatch;
+pub use packer::{Atlas, AtlasBuilder};
+pub use tree2d::{DataSize, PackError, Packer, Tree2d};
+
+use config::Command;
+use packer::Packing;
+
+/// Does what the `corral` command does with `args`, the command line including the program
+/// name, packing once or, with `--watch`, until killed
+///
+/// `--help` prints the usage to stdout instead.
+pub fn run(args: &[String]) -> Result<(), CorralError> {
+    let config = match Command::parse(args)? {
+        Command::Run(config) => config,
+        Command::Help(usage) => {
+            println!("{usage}");
+            return Ok(());
+        }
+    };
+    if config.watch {
+        watch::run(config)
+    } else {
+        packer::run(config).map(|packing| {
+            // Only `--stable` gives these, and says which sprites it had to place
+            if let Packing::Stable { .. } | Packing::Repacked { .. } = packing {
+                eprintln!("{packing}");
+            }
+        })
+    }
+}
@@ -1,19 +1,13 @@
-use std::{env, error::Error, process};
+use std::{env, process};
 
-mod bounding_box;
-mod config;
-mod packer;
-mod tree2d;
-
-use config::Config;
-
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() {
     let args: Vec<String> = env::args().collect();
 
-    let config = Config::parse(&args).unwrap_or_else(|err| {
-        println!("Problem parsing arguments: {err}");
-        process::exit(1);
-    });
-
-    packer::run(config)
+    match corral::run(&args) {
+        Ok(()) => (),
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(err.exit_code());
+        }
+    }
 }
@@ -0,0 +1,750 @@
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::path::{Component, Path};
+
+use crate::config::{MetaDataFormat, MetaDataOptions, MetaDataOutput, UvMode, UvOrigin};
+use crate::error::CorralError;
+use crate::nine_patch::NinePatch;
+
+mod bevy;
+pub(crate) mod binary;
+mod c_header;
+mod cocos2d;
+mod css;
+mod godot;
+mod json;
+mod libgdx;
+mod rust;
+mod starling;
+mod template;
+mod texture_packer;
+mod unity;
+
+pub use template::TemplateFile;
+
+pub struct SpriteData {
+    pub name: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub nine_patch: Option<NinePatch>,
+}
+
+impl SpriteData {
+    /// Splits a trailing `_<number>` off the name, so `walk_3` is frame 3 of `walk`
+    pub fn frame_index(&self) -> (&str, Option<u32>) {
+        if let Some((base, index)) = self.name.rsplit_once('_') {
+            if !base.is_empty() && !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()) {
+                if let Ok(index) = index.parse() {
+                    return (base, Some(index));
+                }
+            }
+        }
+        (&self.name, None)
+    }
+
+    /// Upper case name with spaces replaced, as used for Lua keys and generated constants
+    fn constant_name(&self) -> String {
+        self.name.replace(' ', "_").to_uppercase()
+    }
+
+    /// Bare when the name is a valid Lua identifier, otherwise a quoted `["name"]`
+    fn lua_key(&self, keep_case: bool) -> String {
+        let name = if keep_case {
+            self.name.replace(' ', "_")
+        } else {
+            self.constant_name()
+        };
+        if is_lua_identifier(&name) {
+            name
+        } else {
+            format!("[{name}]", name = quote_lua(&name))
+        }
+    }
+
+    fn to_lua_string(&self, uv: Option<(f64, f64, f64, f64)>, keep_case: bool) -> String {
+        let uv_string = match uv {
+            Some((u0, v0, u1, v1)) => std::format!(
+                "        u0 = {u0},\n        v0 = {v0},\n        u1 = {u1},\n        v1 = {v1},\n"
+            ),
+            None => String::new(),
+        };
+        std::format!(
+            "    {name} = {{
+        x = {x},
+        y = {y},
+        width = {width},
+        height = {height},
+{uv_string}    }}",
+            name = self.lua_key(keep_case),
+            x = self.x,
+            y = self.y,
+            width = self.width,
+            height = self.height
+        )
+    }
+}
+
+const LUA_KEYWORDS: [&str; 22] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+fn is_lua_identifier(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !LUA_KEYWORDS.contains(&text)
+}
+
+/// Double quoted json string
+fn quote_json(text: &str) -> String {
+    serde_json::to_string(text).unwrap()
+}
+
+/// Double quoted Lua string, with anything unprintable written as a decimal escape
+fn quote_lua(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\{:03}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+pub const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// 64 bit FNV-1a, a hash that stays the same across platforms and releases
+pub fn fnv1a(seed: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(seed, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Last component of a path, so sprites can be named without their input directory
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+}
+
+/// Escapes text for XML element content or a double or single quoted attribute
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Path from `from_dir` to `to`, worked out from the path text alone, with `/` separators
+///
+/// Falls back to `to` unchanged when `from_dir` can't be walked back out of,
+/// e.g. it climbs through `..` or only one of the paths is absolute
+fn relative_path(from_dir: &Path, to: &Path) -> String {
+    let from: Vec<Component> = from_dir
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    let to_components: Vec<Component> = to
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    let common = from
+        .iter()
+        .zip(&to_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if from[common..]
+        .iter()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return to.to_string_lossy().replace('\\', "/");
+    }
+    let mut parts: Vec<String> = vec!["..".to_owned(); from.len() - common];
+    parts.extend(
+        to_components[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned()),
+    );
+    parts.join("/")
+}
+
+/// A metadata file to be written alongside the sheet image
+pub struct MetaDataFile {
+    pub path: String,
+    pub contents: Vec<u8>,
+}
+
+#[cfg(test)]
+impl MetaDataFile {
+    pub fn text(&self) -> &str {
+        std::str::from_utf8(&self.contents).unwrap()
+    }
+}
+
+/// The packed sheet as seen by the metadata writers
+pub struct SheetData {
+    pub output_file: String,
+    /// Where the metadata goes instead of next to the sheet image
+    pub meta_data_file: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    /// Space left around each sprite, in pixels
+    pub padding: u8,
+    /// FNV-1a of the sheet's RGBA pixels, so loaders can tell whether the image changed
+    pub image_hash: u64,
+    pub sprites: Vec<SpriteData>,
+}
+
+impl SheetData {
+    pub fn new(
+        output_file: &str,
+        width: u32,
+        height: u32,
+        padding: u8,
+        image_hash: u64,
+        mut sprites: Vec<SpriteData>,
+    ) -> Self {
+        sprites.sort_by(|a, b| a.name.partial_cmp(&b.name).unwrap());
+        SheetData {
+            output_file: output_file.to_owned(),
+            meta_data_file: None,
+            width,
+            height,
+            padding,
+            image_hash,
+            sprites,
+        }
+    }
+
+    /// File name of the sheet image
+    pub fn image_file_name(&self) -> &str {
+        file_name(&self.output_file)
+    }
+
+    /// The sheet image as referenced from the metadata, relative to the metadata's directory
+    pub fn image_path(&self) -> String {
+        match &self.meta_data_file {
+            Some(path) => relative_path(
+                Path::new(path).parent().unwrap_or(Path::new("")),
+                Path::new(&self.output_file),
+            ),
+            None => self.image_file_name().to_owned(),
+        }
+    }
+
+    /// Where single file metadata is written, next to the sheet image unless a path was given
+    fn meta_data_path(&self, extension: &str) -> String {
+        meta_data_path(&self.output_file, self.meta_data_file.as_deref(), extension)
+    }
+
+    /// The sheet image's path without its extension, leaving any dots in its directories
+    pub fn module_name(&self) -> String {
+        module_name(&self.output_file)
+    }
+
+    /// (u0, v0, u1, v1) of a sprite, from the top left of the sheet
+    pub fn uv(&self, sd: &SpriteData) -> (f64, f64, f64, f64) {
+        self.uv_with(sd, UvMode::Exact, UvOrigin::TopLeft)
+    }
+
+    /// (u0, v0, u1, v1) of a sprite, where (u0, v0) is the corner nearest the origin
+    ///
+    /// `Inset` pulls each edge half a texel towards the sprite's centre, so sampling
+    /// with linear filtering never reaches its neighbours
+    pub fn uv_with(&self, sd: &SpriteData, mode: UvMode, origin: UvOrigin) -> (f64, f64, f64, f64) {
+        let inset = match mode {
+            UvMode::Exact => 0.0,
+            UvMode::Inset => 0.5,
+        };
+        let (width, height) = (self.width as f64, self.height as f64);
+        let (x0, x1) = (sd.x as f64 + inset, (sd.x + sd.width) as f64 - inset);
+        let (y0, y1) = (sd.y as f64 + inset, (sd.y + sd.height) as f64 - inset);
+        match origin {
+            UvOrigin::TopLeft => (x0 / width, y0 / height, x1 / width, y1 / height),
+            UvOrigin::BottomLeft => (
+                x0 / width,
+                (height - y1) / height,
+                x1 / width,
+                (height - y0) / height,
+            ),
+        }
+    }
+
+    /// UVs for the json and lua sprites, when asked for
+    fn optional_uv(
+        &self,
+        sd: &SpriteData,
+        options: &MetaDataOptions,
+    ) -> Option<(f64, f64, f64, f64)> {
+        options
+            .uv
+            .map(|mode| self.uv_with(sd, mode, options.uv_origin))
+    }
+
+    /// Numbered sprites grouped by animation name, each sorted by frame index
+    pub fn animations(&self) -> BTreeMap<&str, Vec<(u32, &SpriteData)>> {
+        let mut animations: BTreeMap<&str, Vec<(u32, &SpriteData)>> = BTreeMap::new();
+        for sd in &self.sprites {
+            if let (name, Some(index)) = sd.frame_index() {
+                animations
+                    .entry(file_name(name))
+                    .or_default()
+                    .push((index, sd));
+            }
+        }
+        for frames in animations.values_mut() {
+            frames.sort_by_key(|(index, _)| *index);
+        }
+        animations
+    }
+
+    /// Constant names made into valid identifiers, distinct from each other and from `reserved`
+    fn constant_identifiers(&self, reserved: &[&str]) -> Vec<String> {
+        let mut taken: HashSet<String> = reserved.iter().map(|name| name.to_string()).collect();
+        self.sprites
+            .iter()
+            .map(|sd| {
+                let mut identifier: String = file_name(&sd.constant_name())
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                    .collect();
+                if !identifier.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                    identifier = "SPRITE_".to_owned() + &identifier;
+                }
+                let mut unique = identifier.clone();
+                let mut n = 1;
+                while taken.contains(&unique) {
+                    n += 1;
+                    unique = format!("{identifier}_{n}");
+                }
+                taken.insert(unique.clone());
+                unique
+            })
+            .collect()
+    }
+
+    pub fn to_meta_data_files(
+        &self,
+        format: MetaDataFormat,
+        options: &MetaDataOptions,
+    ) -> Result<Vec<MetaDataFile>, CorralError> {
+        self.to_format_files(format, options)
+            .map_err(|err| match err.downcast::<CorralError>() {
+                Ok(err) => *err,
+                Err(err) => CorralError::MetaData {
+                    format: format.name(),
+                    message: err.to_string(),
+                },
+            })
+    }
+
+    fn to_format_files(
+        &self,
+        format: MetaDataFormat,
+        options: &MetaDataOptions,
+    ) -> Result<Vec<MetaDataFile>, Box<dyn Error>> {
+        let contents = match format {
+            MetaDataFormat::Json => json::to_json_string(self, options)?,
+            MetaDataFormat::Lua => self.to_lua_string(options),
+            MetaDataFormat::TexturePackerHash => texture_packer::to_hash_string(self),
+            MetaDataFormat::TexturePackerArray => texture_packer::to_array_string(self),
+            MetaDataFormat::LibGdx => libgdx::to_atlas_string(self),
+            MetaDataFormat::Starling => starling::to_xml_string(self),
+            MetaDataFormat::Godot => return Ok(godot::to_resource_files(self)),
+            MetaDataFormat::Bevy => bevy::to_ron_string(self),
+            MetaDataFormat::Unity => return Ok(vec![unity::to_meta_file(self)]),
+            MetaDataFormat::Cocos2d => cocos2d::to_plist_string(self),
+            MetaDataFormat::Css => css::to_css_string(self),
+            MetaDataFormat::Scss => css::to_scss_string(self),
+            MetaDataFormat::Rust => rust::to_module_string(self),
+            MetaDataFormat::CHeader => c_header::to_header_string(self, options),
+            MetaDataFormat::Template => return template::to_template_file(self, options),
+            MetaDataFormat::Binary => return self.single_file(format, binary::to_binary(self)),
+            MetaDataFormat::MsgPack => {
+                return self.single_file(format, json::to_msgpack(self, options)?)
+            }
+            MetaDataFormat::Cbor => return self.single_file(format, json::to_cbor(self, options)?),
+        };
+        self.single_file(format, contents.into_bytes())
+    }
+
+    fn single_file(
+        &self,
+        format: MetaDataFormat,
+        contents: Vec<u8>,
+    ) -> Result<Vec<MetaDataFile>, Box<dyn Error>> {
+        Ok(vec![MetaDataFile {
+            path: self.meta_data_path(file_extension(format)),
+            contents,
+        }])
+    }
+
+    fn to_lua_string(&self, options: &MetaDataOptions) -> String {
+        // The sheet's file name, made into a valid local variable name
+        let mut module_name: String = file_name(&self.module_name())
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if !is_lua_identifier(&module_name) {
+            module_name = "_".to_owned() + &module_name;
+        }
+        let lua_string: String = self
+            .sprites
+            .iter()
+            .map(|sd| sd.to_lua_string(self.optional_uv(sd, options), options.keep_case))
+            .collect::<Vec<String>>()
+            .join(",\n");
+        format!("local {fname} = {{\n", fname = module_name)
+            + &lua_string
+            + &format!("\n}}\n\nreturn {fname}\n", fname = module_name)
+    }
+}
+
+fn file_extension(format: MetaDataFormat) -> &'static str {
+    match format {
+        MetaDataFormat::Json
+        | MetaDataFormat::TexturePackerHash
+        | MetaDataFormat::TexturePackerArray => ".json",
+        MetaDataFormat::Lua => ".lua",
+        MetaDataFormat::LibGdx => ".atlas",
+        MetaDataFormat::Starling => ".xml",
+        MetaDataFormat::Godot => ".tres",
+        MetaDataFormat::Bevy => ".ron",
+        MetaDataFormat::Unity => ".meta",
+        MetaDataFormat::Cocos2d => ".plist",
+        MetaDataFormat::Css => ".css",
+        MetaDataFormat::Scss => ".scss",
+        MetaDataFormat::Rust => ".rs",
+        MetaDataFormat::CHeader => ".h",
+        MetaDataFormat::Template => ".txt",
+        MetaDataFormat::Binary => ".bin",
+        MetaDataFormat::MsgPack => ".msgpack",
+        MetaDataFormat::Cbor => ".cbor",
+    }
+}
+
+/// The sheet image's path without its extension, leaving any dots in its directories
+fn module_name(output_file: &str) -> String {
+    Path::new(output_file)
+        .with_extension("")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Where single file metadata is written, next to the sheet image unless a path was given
+fn meta_data_path(output_file: &str, meta_data_file: Option<&str>, extension: &str) -> String {
+    match meta_data_file {
+        Some(path) => path.to_owned(),
+        None => module_name(output_file) + extension,
+    }
+}
+
+/// Where an output writes its main file, the one a path given with `--data-fmt` replaces
+pub fn output_path(
+    output_file: &str,
+    output: &MetaDataOutput,
+    options: &MetaDataOptions,
+) -> String {
+    let path = output.path.as_deref();
+    match output.format {
+        MetaDataFormat::Unity => unity::meta_file_path(output_file, path),
+        MetaDataFormat::Template => match &options.template {
+            Some(template_file) => meta_data_path(
+                output_file,
+                path,
+                &template::output_extension(&template_file.path),
+            ),
+            None => meta_data_path(output_file, path, file_extension(output.format)),
+        },
+        format => meta_data_path(output_file, path, file_extension(format)),
+    }
+}
+
+/// The directory an output writes its other files into, for Godot's `AtlasTexture`s
+pub fn output_dir(
+    output_file: &str,
+    output: &MetaDataOutput,
+    options: &MetaDataOptions,
+) -> Option<String> {
+    match output.format {
+        MetaDataFormat::Godot => Some(
+            godot::resource_dir(&output_path(output_file, output, options))
+                .to_string_lossy()
+                .into_owned(),
+        ),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub fn make_sheet() -> SheetData {
+        SheetData::new(
+            "out/sheet.png",
+            70,
+            40,
+            2,
+            0x0123_4567_89ab_cdef,
+            vec![
+                SpriteData {
+                    name: "b".to_owned(),
+                    x: 36,
+                    y: 2,
+                    width: 32,
+                    height: 16,
+                    nine_patch: None,
+                },
+                SpriteData {
+                    name: "a".to_owned(),
+                    x: 2,
+                    y: 2,
+                    width: 32,
+                    height: 32,
+                    nine_patch: None,
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn sprites_sorted_by_name() {
+        let sheet = make_sheet();
+        let names: Vec<&str> = sheet.sprites.iter().map(|sd| sd.name.as_str()).collect();
+        assert_eq!(vec!["a", "b"], names);
+    }
+
+    #[test]
+    fn frame_index() {
+        let mut sd = make_sheet().sprites.remove(0);
+        for (name, expected) in [
+            ("walk_3", ("walk", Some(3))),
+            ("walk_cycle_12", ("walk_cycle", Some(12))),
+            ("walk", ("walk", None)),
+            ("walk_", ("walk_", None)),
+            ("_3", ("_3", None)),
+            ("walk_x3", ("walk_x3", None)),
+        ] {
+            sd.name = name.to_owned();
+            assert_eq!(expected, sd.frame_index(), "{name}");
+        }
+    }
+
+    #[test]
+    fn valid_distinct_identifiers() {
+        let mut sheet = make_sheet();
+        sheet.sprites[0].name = "dir/1-up".to_owned();
+        sheet.sprites[1].name = "sprites".to_owned();
+        sheet.sprites.push(SpriteData {
+            name: "1.up".to_owned(),
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+            nine_patch: None,
+        });
+        assert_eq!(
+            vec!["SPRITE_1_UP", "SPRITES_2", "SPRITE_1_UP_2"],
+            sheet.constant_identifiers(&["SPRITES"])
+        );
+    }
+
+    #[test]
+    fn uv_modes() {
+        let mut sheet = make_sheet();
+        sheet.width = 64;
+        sheet.height = 32;
+        let sd = &sheet.sprites[1];
+        assert_eq!(
+            (0.5625, 0.0625, 1.0625, 0.5625),
+            sheet.uv_with(sd, UvMode::Exact, UvOrigin::TopLeft)
+        );
+        assert_eq!(
+            (0.5625, 0.4375, 1.0625, 0.9375),
+            sheet.uv_with(sd, UvMode::Exact, UvOrigin::BottomLeft)
+        );
+        assert_eq!(
+            (0.5703125, 0.078125, 1.0546875, 0.546875),
+            sheet.uv_with(sd, UvMode::Inset, UvOrigin::TopLeft)
+        );
+        assert_eq!(
+            (0.5703125, 0.453125, 1.0546875, 0.921875),
+            sheet.uv_with(sd, UvMode::Inset, UvOrigin::BottomLeft)
+        );
+    }
+
+    #[test]
+    fn json_uv() -> Result<(), Box<dyn Error>> {
+        let options = MetaDataOptions {
+            json_array: true,
+            uv: Some(UvMode::Exact),
+            ..Default::default()
+        };
+        let mut sheet = make_sheet();
+        sheet.sprites.truncate(1);
+        let files = sheet.to_meta_data_files(MetaDataFormat::Json, &options)?;
+        assert_eq!(
+            "[{\"height\":32,\"name\":\"a\",\"width\":32,\"x\":2,\"y\":2,\"u0\":0.02857142857142857,\"v0\":0.05,\"u1\":0.4857142857142857,\"v1\":0.85}]\n",
+            files[0].text()
+        );
+        let files = sheet.to_meta_data_files(MetaDataFormat::Lua, &options)?;
+        assert!(files[0]
+            .text()
+            .contains("        height = 32,\n        u0 = 0.02857142857142857,\n        v0 = 0.05,\n        u1 = 0.4857142857142857,\n        v1 = 0.85,\n    }"));
+        Ok(())
+    }
+
+    #[test]
+    fn lua_keys() {
+        let mut sd = make_sheet().sprites.remove(0);
+        for (name, expected, expected_kept_case) in [
+            ("walk", "WALK", "walk"),
+            ("walk cycle", "WALK_CYCLE", "walk_cycle"),
+            ("1up", "[\"1UP\"]", "[\"1up\"]"),
+            ("dash-ed.png", "[\"DASH-ED.PNG\"]", "[\"dash-ed.png\"]"),
+            ("end", "END", "[\"end\"]"),
+            (
+                "a\"b\\c\n\t",
+                "[\"A\\\"B\\\\C\\n\\009\"]",
+                "[\"a\\\"b\\\\c\\n\\009\"]",
+            ),
+        ] {
+            sd.name = name.to_owned();
+            assert_eq!(expected, sd.lua_key(false), "{name}");
+            assert_eq!(expected_kept_case, sd.lua_key(true), "{name}");
+        }
+    }
+
+    #[test]
+    fn lua() -> Result<(), Box<dyn Error>> {
+        let mut sheet = make_sheet();
+        sheet.sprites.truncate(1);
+        let files = sheet.to_meta_data_files(MetaDataFormat::Lua, &MetaDataOptions::default())?;
+        assert_eq!("out/sheet.lua", files[0].path);
+        assert_eq!(
+            "local sheet = {\n    A = {\n        x = 2,\n        y = 2,\n        width = 32,\n        height = 32,\n    }\n}\n\nreturn sheet\n",
+            files[0].text()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fnv1a_hash() {
+        assert_eq!(FNV_OFFSET_BASIS, fnv1a(FNV_OFFSET_BASIS, b""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, fnv1a(FNV_OFFSET_BASIS, b"a"));
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            "&lt;a&gt; &amp; &quot;b&quot; &apos;c&apos;",
+            escape_xml("<a> & \"b\" 'c'")
+        );
+    }
+
+    #[test]
+    fn image_file_name() {
+        assert_eq!("sheet.png", make_sheet().image_file_name());
+    }
+
+    #[test]
+    fn image_path() {
+        let mut sheet = make_sheet();
+        assert_eq!("sheet.png", sheet.image_path());
+        sheet.meta_data_file = Some("out/data/sheet.json".to_owned());
+        assert_eq!("../sheet.png", sheet.image_path());
+        sheet.meta_data_file = Some("sheet.json".to_owned());
+        assert_eq!("out/sheet.png", sheet.image_path());
+    }
+
+    #[test]
+    fn relative_paths() {
+        for (from_dir, to, expected) in [
+            ("a/b", "a/b/c.png", "c.png"),
+            ("a/b", "a/c/d.png", "../c/d.png"),
+            ("./a", "a/c.png", "c.png"),
+            ("", "a/c.png", "a/c.png"),
+            ("../a", "c.png", "c.png"),
+            ("/a", "c.png", "c.png"),
+            ("/a/b", "/a/c.png", "../c.png"),
+        ] {
+            assert_eq!(
+                expected,
+                relative_path(Path::new(from_dir), Path::new(to)),
+                "{from_dir} -> {to}"
+            );
+        }
+    }
+
+    #[test]
+    fn custom_path() -> Result<(), Box<dyn Error>> {
+        let mut sheet = make_sheet();
+        sheet.meta_data_file = Some("game/sprites.lua".to_owned());
+        let files = sheet.to_meta_data_files(MetaDataFormat::Lua, &MetaDataOptions::default())?;
+        assert_eq!(1, files.len());
+        assert_eq!("game/sprites.lua", files[0].path);
+        Ok(())
+    }
+
+    #[test]
+    fn animations() {
+        let mut sheet = make_sheet();
+        sheet.sprites[0].name = "walk_1".to_owned();
+        sheet.sprites[1].name = "dir/walk_0".to_owned();
+        let animations = sheet.animations();
+        let frames: Vec<(u32, &str)> = animations["walk"]
+            .iter()
+            .map(|(index, sd)| (*index, sd.name.as_str()))
+            .collect();
+        assert_eq!(vec![(0, "dir/walk_0"), (1, "walk_1")], frames);
+    }
+
+    #[test]
+    fn json() -> Result<(), Box<dyn Error>> {
+        let files =
+            make_sheet().to_meta_data_files(MetaDataFormat::Json, &MetaDataOptions::default())?;
+        assert_eq!(1, files.len());
+        assert_eq!("out/sheet.json", files[0].path);
+        assert_eq!(
+            format!(
+                "{{\"meta\":{{\"app\":\"{app}\",\"version\":\"{version}\",\"image\":\"sheet.png\",\"format\":\"RGBA8888\",\"width\":70,\"height\":40,\"padding\":2,\"scale\":1,\"hash\":\"0123456789abcdef\"}},\"sprites\":[{{\"height\":32,\"name\":\"a\",\"width\":32,\"x\":2,\"y\":2}},{{\"height\":16,\"name\":\"b\",\"width\":32,\"x\":36,\"y\":2}}]}}\n",
+                app = env!("CARGO_PKG_HOMEPAGE"),
+                version = env!("CARGO_PKG_VERSION")
+            ),
+            files[0].text()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn json_array() -> Result<(), Box<dyn Error>> {
+        let options = MetaDataOptions {
+            json_array: true,
+            ..Default::default()
+        };
+        let files = make_sheet().to_meta_data_files(MetaDataFormat::Json, &options)?;
+        assert_eq!(
+            "[{\"height\":32,\"name\":\"a\",\"width\":32,\"x\":2,\"y\":2},{\"height\":16,\"name\":\"b\",\"width\":32,\"x\":36,\"y\":2}]\n",
+            files[0].text()
+        );
+        Ok(())
+    }
+}
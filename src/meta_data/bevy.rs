@@ -0,0 +1,82 @@
+//! Bevy `TextureAtlasLayout` in RON, alongside a map from sprite name to texture index
+
+use super::{SheetData, SpriteData};
+
+impl SpriteData {
+    fn to_bevy_urect_string(&self) -> String {
+        std::format!(
+            "(min: ({x}, {y}), max: ({max_x}, {max_y}))",
+            x = self.x,
+            y = self.y,
+            max_x = self.x + self.width,
+            max_y = self.y + self.height
+        )
+    }
+}
+
+fn escape_ron(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Deserializes into `struct { layout: TextureAtlasLayout, names: HashMap<String, usize> }`
+pub fn to_ron_string(sheet: &SheetData) -> String {
+    let textures: String = sheet
+        .sprites
+        .iter()
+        .map(|sd| format!("            {urect},\n", urect = sd.to_bevy_urect_string()))
+        .collect();
+    let names: String = sheet
+        .sprites
+        .iter()
+        .enumerate()
+        .map(|(index, sd)| {
+            format!(
+                "        \"{name}\": {index},\n",
+                name = escape_ron(&sd.name.replace(' ', "_"))
+            )
+        })
+        .collect();
+    format!(
+        "(
+    layout: (
+        size: ({width}, {height}),
+        textures: [
+{textures}        ],
+    ),
+    names: {{
+{names}    }},
+)
+",
+        width = sheet.width,
+        height = sheet.height
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_data::tests::make_sheet;
+
+    #[test]
+    fn ron() {
+        let mut sheet = make_sheet();
+        sheet.sprites[1].name = "b \"quoted\"".to_owned();
+        assert_eq!(
+            "(
+    layout: (
+        size: (70, 40),
+        textures: [
+            (min: (2, 2), max: (34, 34)),
+            (min: (36, 2), max: (68, 18)),
+        ],
+    ),
+    names: {
+        \"a\": 0,
+        \"b_\\\"quoted\\\"\": 1,
+    },
+)
+",
+            to_ron_string(&sheet)
+        );
+    }
+}
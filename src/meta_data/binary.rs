@@ -0,0 +1,364 @@
+//! A compact little-endian atlas, laid out so it can be read in place without parsing
+//!
+//! Every field is a `u32` and every table starts on a 4 byte boundary. Offsets are from the
+//! start of the file, except string offsets, which are from the start of the string table.
+//!
+//! | offset | header field                       |
+//! |--------|------------------------------------|
+//! | 0      | magic, `CRAL`                      |
+//! | 4      | version, currently 1               |
+//! | 8      | sheet width                        |
+//! | 12     | sheet height                       |
+//! | 16     | image path offset and length       |
+//! | 24     | sprite count and table offset      |
+//! | 32     | animation count and table offset   |
+//! | 40     | frame count and table offset       |
+//! | 48     | string table offset and length     |
+//!
+//! Sprites are 24 bytes each: name offset and length, x, y, width and height. Animations are
+//! 16 bytes each: name offset and length, first frame and frame count. Frames are sprite
+//! indices, and strings are UTF-8 without terminators.
+
+use std::collections::HashMap;
+
+use super::SheetData;
+
+pub const MAGIC: &[u8; 4] = b"CRAL";
+pub const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 56;
+const SPRITE_SIZE: usize = 24;
+const ANIMATION_SIZE: usize = 16;
+const FRAME_SIZE: usize = 4;
+
+#[derive(Default)]
+struct StringTable {
+    bytes: Vec<u8>,
+}
+
+impl StringTable {
+    /// (offset, length) of the added string
+    fn add(&mut self, text: &str) -> [u32; 2] {
+        let offset = self.bytes.len() as u32;
+        self.bytes.extend_from_slice(text.as_bytes());
+        [offset, text.len() as u32]
+    }
+}
+
+fn push_u32s(bytes: &mut Vec<u8>, values: &[u32]) {
+    for value in values {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+pub fn to_binary(sheet: &SheetData) -> Vec<u8> {
+    let mut strings = StringTable::default();
+    let image = strings.add(&sheet.image_path());
+
+    let mut sprites = vec![];
+    for sd in &sheet.sprites {
+        let [name_offset, name_len] = strings.add(&sd.name.replace(' ', "_"));
+        push_u32s(
+            &mut sprites,
+            &[name_offset, name_len, sd.x, sd.y, sd.width, sd.height],
+        );
+    }
+
+    // Frames refer to sprites by their index in the sprite table
+    let mut sprite_indices: HashMap<&str, u32> = HashMap::new();
+    for (index, sd) in sheet.sprites.iter().enumerate() {
+        sprite_indices.entry(&sd.name).or_insert(index as u32);
+    }
+
+    let mut animations = vec![];
+    let mut frames = vec![];
+    let mut frame_count = 0;
+    let sheet_animations = sheet.animations();
+    for (name, animation_frames) in &sheet_animations {
+        let [name_offset, name_len] = strings.add(name);
+        let first_frame = frame_count;
+        for (_, sd) in animation_frames {
+            push_u32s(&mut frames, &[sprite_indices[sd.name.as_str()]]);
+            frame_count += 1;
+        }
+        push_u32s(
+            &mut animations,
+            &[
+                name_offset,
+                name_len,
+                first_frame,
+                frame_count - first_frame,
+            ],
+        );
+    }
+
+    let sprites_offset = HEADER_SIZE;
+    let animations_offset = sprites_offset + sprites.len();
+    let frames_offset = animations_offset + animations.len();
+    let strings_offset = frames_offset + frames.len();
+
+    let mut bytes = Vec::with_capacity(strings_offset + strings.bytes.len());
+    bytes.extend_from_slice(MAGIC);
+    push_u32s(
+        &mut bytes,
+        &[
+            VERSION,
+            sheet.width,
+            sheet.height,
+            image[0],
+            image[1],
+            sheet.sprites.len() as u32,
+            sprites_offset as u32,
+            sheet_animations.len() as u32,
+            animations_offset as u32,
+            frame_count,
+            frames_offset as u32,
+            strings_offset as u32,
+            strings.bytes.len() as u32,
+        ],
+    );
+    bytes.extend_from_slice(&sprites);
+    bytes.extend_from_slice(&animations);
+    bytes.extend_from_slice(&frames);
+    bytes.extend_from_slice(&strings.bytes);
+    bytes
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    let mut le_bytes = [0; 4];
+    le_bytes.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(le_bytes)
+}
+
+/// A sprite read from a binary atlas, its name borrowed from the atlas bytes
+#[derive(Debug, PartialEq, Eq)]
+pub struct BinarySprite<'a> {
+    pub name: &'a str,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// An animation read from a binary atlas
+#[derive(Debug, PartialEq, Eq)]
+pub struct BinaryAnimation<'a> {
+    pub name: &'a str,
+    frames: &'a [u8],
+}
+
+impl BinaryAnimation<'_> {
+    /// Sprite indices, in frame order
+    pub fn frames(&self) -> impl Iterator<Item = u32> + '_ {
+        self.frames
+            .chunks_exact(FRAME_SIZE)
+            .map(|frame| u32_at(frame, 0))
+    }
+}
+
+/// Reads a binary atlas in place, everything is checked up front so lookups never fail
+pub struct BinaryAtlas<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> BinaryAtlas<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<BinaryAtlas<'a>, &'static str> {
+        if bytes.len() < HEADER_SIZE || &bytes[0..4] != MAGIC {
+            return Err("Not a corral binary atlas");
+        }
+        if u32_at(bytes, 4) != VERSION {
+            return Err("Unsupported binary atlas version");
+        }
+        let atlas = BinaryAtlas { bytes };
+
+        let table = |count_offset: usize, record_size: usize| {
+            let count = u32_at(bytes, count_offset) as usize;
+            let offset = u32_at(bytes, count_offset + 4) as usize;
+            count
+                .checked_mul(record_size)
+                .and_then(|size| size.checked_add(offset))
+                .filter(|end| offset % 4 == 0 && *end <= bytes.len())
+                .map(|_| ())
+                .ok_or("Binary atlas table out of bounds")
+        };
+        table(24, SPRITE_SIZE)?;
+        table(32, ANIMATION_SIZE)?;
+        table(40, FRAME_SIZE)?;
+        let strings_offset = u32_at(bytes, 48) as usize;
+        if strings_offset.saturating_add(u32_at(bytes, 52) as usize) > bytes.len() {
+            return Err("Binary atlas string table out of bounds");
+        }
+
+        let string = |offset: usize| atlas.checked_string(offset).map(|_| ());
+        string(16)?;
+        for i in 0..atlas.sprite_count() {
+            string(atlas.sprite_offset(i))?;
+        }
+        for i in 0..atlas.animation_count() {
+            let offset = atlas.animation_offset(i);
+            string(offset)?;
+            let first_frame = u32_at(bytes, offset + 8) as usize;
+            let frame_count = u32_at(bytes, offset + 12) as usize;
+            if first_frame.saturating_add(frame_count) > u32_at(bytes, 40) as usize {
+                return Err("Binary atlas animation frames out of bounds");
+            }
+        }
+        let frames_offset = u32_at(bytes, 44) as usize;
+        for i in 0..u32_at(bytes, 40) as usize {
+            if u32_at(bytes, frames_offset + i * FRAME_SIZE) as usize >= atlas.sprite_count() {
+                return Err("Binary atlas frame refers to a missing sprite");
+            }
+        }
+        Ok(atlas)
+    }
+
+    /// The string whose offset and length are stored at `offset`
+    fn checked_string(&self, offset: usize) -> Result<&'a str, &'static str> {
+        let strings_offset = u32_at(self.bytes, 48) as usize;
+        let strings_len = u32_at(self.bytes, 52) as usize;
+        let start = u32_at(self.bytes, offset) as usize;
+        let len = u32_at(self.bytes, offset + 4) as usize;
+        if start.saturating_add(len) > strings_len {
+            return Err("Binary atlas string out of bounds");
+        }
+        let start = strings_offset + start;
+        std::str::from_utf8(&self.bytes[start..start + len])
+            .map_err(|_| "Binary atlas string is not UTF-8")
+    }
+
+    fn string(&self, offset: usize) -> &'a str {
+        self.checked_string(offset).unwrap_or_default()
+    }
+
+    fn sprite_offset(&self, index: usize) -> usize {
+        u32_at(self.bytes, 28) as usize + index * SPRITE_SIZE
+    }
+
+    fn animation_offset(&self, index: usize) -> usize {
+        u32_at(self.bytes, 36) as usize + index * ANIMATION_SIZE
+    }
+
+    pub fn width(&self) -> u32 {
+        u32_at(self.bytes, 8)
+    }
+
+    pub fn height(&self) -> u32 {
+        u32_at(self.bytes, 12)
+    }
+
+    /// The sheet image, relative to the atlas file
+    pub fn image(&self) -> &'a str {
+        self.string(16)
+    }
+
+    pub fn sprite_count(&self) -> usize {
+        u32_at(self.bytes, 24) as usize
+    }
+
+    pub fn sprite(&self, index: usize) -> Option<BinarySprite<'a>> {
+        if index >= self.sprite_count() {
+            return None;
+        }
+        let offset = self.sprite_offset(index);
+        Some(BinarySprite {
+            name: self.string(offset),
+            x: u32_at(self.bytes, offset + 8),
+            y: u32_at(self.bytes, offset + 12),
+            width: u32_at(self.bytes, offset + 16),
+            height: u32_at(self.bytes, offset + 20),
+        })
+    }
+
+    pub fn sprites(&self) -> impl Iterator<Item = BinarySprite<'a>> + '_ {
+        (0..self.sprite_count()).filter_map(|index| self.sprite(index))
+    }
+
+    pub fn animation_count(&self) -> usize {
+        u32_at(self.bytes, 32) as usize
+    }
+
+    pub fn animation(&self, index: usize) -> Option<BinaryAnimation<'a>> {
+        if index >= self.animation_count() {
+            return None;
+        }
+        let offset = self.animation_offset(index);
+        let first_frame = u32_at(self.bytes, offset + 8) as usize;
+        let frame_count = u32_at(self.bytes, offset + 12) as usize;
+        let frames_start = u32_at(self.bytes, 44) as usize + first_frame * FRAME_SIZE;
+        Some(BinaryAnimation {
+            name: self.string(offset),
+            frames: &self.bytes[frames_start..frames_start + frame_count * FRAME_SIZE],
+        })
+    }
+
+    pub fn animations(&self) -> impl Iterator<Item = BinaryAnimation<'a>> + '_ {
+        (0..self.animation_count()).filter_map(|index| self.animation(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MetaDataOptions;
+    use crate::meta_data::json;
+    use crate::meta_data::tests::make_sheet;
+    use std::error::Error;
+
+    #[test]
+    fn round_trip() -> Result<(), Box<dyn Error>> {
+        let mut sheet = make_sheet();
+        sheet.sprites[0].name = "dir/walk_1".to_owned();
+        sheet.sprites[1].name = "walk_0".to_owned();
+        let bytes = to_binary(&sheet);
+        let atlas = BinaryAtlas::parse(&bytes)?;
+
+        let json: serde_json::Value =
+            serde_json::from_str(&json::to_json_string(&sheet, &MetaDataOptions::default())?)?;
+        assert_eq!(json["meta"]["width"], atlas.width());
+        assert_eq!(json["meta"]["height"], atlas.height());
+        assert_eq!(json["meta"]["image"], atlas.image());
+        let json_sprites = json["sprites"].as_array().ok_or("no sprites")?;
+        assert_eq!(json_sprites.len(), atlas.sprite_count());
+        for (json_sprite, sprite) in json_sprites.iter().zip(atlas.sprites()) {
+            assert_eq!(json_sprite["name"], sprite.name);
+            assert_eq!(json_sprite["x"], sprite.x);
+            assert_eq!(json_sprite["y"], sprite.y);
+            assert_eq!(json_sprite["width"], sprite.width);
+            assert_eq!(json_sprite["height"], sprite.height);
+        }
+
+        assert_eq!(1, atlas.animation_count());
+        let walk = atlas.animation(0).ok_or("no animation")?;
+        assert_eq!("walk", walk.name);
+        assert_eq!(vec![1, 0], walk.frames().collect::<Vec<u32>>());
+        assert_eq!(None, atlas.sprite(2));
+        assert_eq!(None, atlas.animation(1));
+        Ok(())
+    }
+
+    #[test]
+    fn layout() {
+        let bytes = to_binary(&make_sheet());
+        assert_eq!(b"CRAL", &bytes[0..4]);
+        assert_eq!(VERSION, u32_at(&bytes, 4));
+        assert_eq!(70, u32_at(&bytes, 8));
+        // Header, two sprites, then the strings "sheet.png", "a" and "b"
+        assert_eq!(HEADER_SIZE + 2 * SPRITE_SIZE + 11, bytes.len());
+        assert_eq!(b"sheet.pngab", &bytes[bytes.len() - 11..]);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let bytes = to_binary(&make_sheet());
+        assert!(BinaryAtlas::parse(&bytes[..HEADER_SIZE - 1]).is_err());
+        assert!(BinaryAtlas::parse(&bytes[..bytes.len() - 1]).is_err());
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        assert!(BinaryAtlas::parse(&wrong_magic).is_err());
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 2;
+        assert!(BinaryAtlas::parse(&wrong_version).is_err());
+        let mut long_name = bytes;
+        long_name[HEADER_SIZE + 4] = 100;
+        assert!(BinaryAtlas::parse(&long_name).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! Single C/C++ header with an enum of sprite ids and a table of their rectangles
+
+use super::{file_name, SheetData};
+use crate::config::MetaDataOptions;
+
+/// Upper case names the header declares besides the sprite ids, its macros and include guard
+/// among them, which a sprite id of the same name would expand into
+const RESERVED_NAMES: [&str; 5] = [
+    "SPRITE_COUNT",
+    "SPRITES_H",
+    "SHEET_IMAGE",
+    "SHEET_WIDTH",
+    "SHEET_HEIGHT",
+];
+
+/// Escapes text for a C string literal, keeping UTF-8 as is
+fn escape_c(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            c if c.is_ascii_control() => escaped.push_str(&format!("\\{:03o}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Defaults to the sheet's file name, so headers of different sheets can be included together
+fn prefix(sheet: &SheetData, options: &MetaDataOptions) -> String {
+    match &options.prefix {
+        Some(prefix) => prefix.to_owned(),
+        None => {
+            let name: String = file_name(&sheet.module_name())
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                name + "_"
+            } else {
+                "sheet_".to_owned() + &name + "_"
+            }
+        }
+    }
+}
+
+pub fn to_header_string(sheet: &SheetData, options: &MetaDataOptions) -> String {
+    let prefix = prefix(sheet, options);
+    let upper_prefix = prefix.to_uppercase();
+    let identifiers = sheet.constant_identifiers(&RESERVED_NAMES);
+
+    let ids: String = identifiers
+        .iter()
+        .map(|identifier| format!("    {upper_prefix}{identifier},\n"))
+        .collect();
+    let sprites: String = sheet
+        .sprites
+        .iter()
+        .map(|sd| {
+            let (u0, v0, u1, v1) = sheet.uv(sd);
+            let (u0, v0, u1, v1) = (u0 as f32, v0 as f32, u1 as f32, v1 as f32);
+            format!(
+                "    {{{x}, {y}, {w}, {h}, {u0:?}f, {v0:?}f, {u1:?}f, {v1:?}f}},\n",
+                x = sd.x,
+                y = sd.y,
+                w = sd.width,
+                h = sd.height
+            )
+        })
+        .collect();
+    let names: String = sheet
+        .sprites
+        .iter()
+        .map(|sd| format!("    \"{name}\",\n", name = escape_c(&sd.name)))
+        .collect();
+
+    format!(
+        "/* Generated by corral, do not edit */
+#ifndef {upper_prefix}SPRITES_H
+#define {upper_prefix}SPRITES_H
+
+#define {upper_prefix}SHEET_IMAGE \"{image}\"
+#define {upper_prefix}SHEET_WIDTH {sheet_width}
+#define {upper_prefix}SHEET_HEIGHT {sheet_height}
+
+typedef struct {prefix}sprite {{
+    unsigned int x, y, w, h;
+    /* from the top left of the sheet */
+    float u0, v0, u1, v1;
+}} {prefix}sprite;
+
+enum {prefix}sprite_id {{
+{ids}    {upper_prefix}SPRITE_COUNT
+}};
+
+/* indexed by {prefix}sprite_id */
+static const {prefix}sprite {prefix}sprites[{upper_prefix}SPRITE_COUNT] = {{
+{sprites}}};
+
+/* indexed by {prefix}sprite_id, sorted */
+static const char *const {prefix}sprite_names[{upper_prefix}SPRITE_COUNT] = {{
+{names}}};
+
+#endif /* {upper_prefix}SPRITES_H */
+",
+        image = escape_c(&sheet.image_path()),
+        sheet_width = sheet.width,
+        sheet_height = sheet.height
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_data::tests::make_sheet;
+    use crate::meta_data::SpriteData;
+
+    #[test]
+    fn escape() {
+        assert_eq!("a\\\\b\\\"c\\\"\\012", escape_c("a\\b\"c\"\n"));
+    }
+
+    #[test]
+    fn default_prefix() {
+        let options = MetaDataOptions::default();
+        let mut sheet = make_sheet();
+        assert_eq!("sheet_", prefix(&sheet, &options));
+        sheet.output_file = "2 Sheets.png".to_owned();
+        assert_eq!("sheet_2_sheets_", prefix(&sheet, &options));
+    }
+
+    #[test]
+    fn header() {
+        let options = MetaDataOptions {
+            prefix: Some("game_".to_owned()),
+            ..MetaDataOptions::default()
+        };
+        assert_eq!(
+            "/* Generated by corral, do not edit */
+#ifndef GAME_SPRITES_H
+#define GAME_SPRITES_H
+
+#define GAME_SHEET_IMAGE \"sheet.png\"
+#define GAME_SHEET_WIDTH 70
+#define GAME_SHEET_HEIGHT 40
+
+typedef struct game_sprite {
+    unsigned int x, y, w, h;
+    /* from the top left of the sheet */
+    float u0, v0, u1, v1;
+} game_sprite;
+
+enum game_sprite_id {
+    GAME_A,
+    GAME_B,
+    GAME_SPRITE_COUNT
+};
+
+/* indexed by game_sprite_id */
+static const game_sprite game_sprites[GAME_SPRITE_COUNT] = {
+    {2, 2, 32, 32, 0.028571429f, 0.05f, 0.4857143f, 0.85f},
+    {36, 2, 32, 16, 0.51428574f, 0.05f, 0.9714286f, 0.45f},
+};
+
+/* indexed by game_sprite_id, sorted */
+static const char *const game_sprite_names[GAME_SPRITE_COUNT] = {
+    \"a\",
+    \"b\",
+};
+
+#endif /* GAME_SPRITES_H */
+",
+            to_header_string(&make_sheet(), &options)
+        );
+    }
+
+    #[test]
+    fn ids_distinct_from_macros() {
+        let mut sheet = make_sheet();
+        sheet.sprites.clear();
+        for name in [
+            "sheet_width",
+            "sheet_height",
+            "sheet_image",
+            "sprites_h",
+            "sprite_count",
+        ] {
+            sheet.sprites.push(SpriteData {
+                name: "in/".to_owned() + name,
+                x: 0,
+                y: 0,
+                width: 1,
+                height: 1,
+                nine_patch: None,
+            });
+        }
+        let header = to_header_string(&sheet, &MetaDataOptions::default());
+        let macros: Vec<&str> = header
+            .lines()
+            .filter_map(|line| line.strip_prefix("#define "))
+            .filter_map(|line| line.split_whitespace().next())
+            .collect();
+        let enum_body = header
+            .split_once("enum sheet_sprite_id {\n")
+            .and_then(|(_, rest)| rest.split_once("};"))
+            .map(|(body, _)| body)
+            .unwrap();
+        let ids: Vec<&str> = enum_body
+            .split(',')
+            .map(|id| id.trim())
+            .filter(|id| !id.is_empty())
+            .collect();
+        assert_eq!(6, ids.len());
+        for id in &ids {
+            assert!(!macros.contains(id), "{id} is also a macro");
+            assert_eq!(1, ids.iter().filter(|other| *other == id).count(), "{id}");
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Apple property list in cocos2d-x's sprite frame format 3
+
+use super::{escape_xml, SheetData, SpriteData};
+
+impl SpriteData {
+    fn to_cocos2d_frame_string(&self) -> String {
+        std::format!(
+            "            <key>{name}</key>
+            <dict>
+                <key>aliases</key>
+                <array/>
+                <key>spriteOffset</key>
+                <string>{{0,0}}</string>
+                <key>spriteSize</key>
+                <string>{{{width},{height}}}</string>
+                <key>spriteSourceSize</key>
+                <string>{{{width},{height}}}</string>
+                <key>textureRect</key>
+                <string>{{{{{x},{y}}},{{{width},{height}}}}}</string>
+                <key>textureRotated</key>
+                <false/>
+            </dict>
+",
+            name = escape_xml(&self.name.replace(' ', "_")),
+            x = self.x,
+            y = self.y,
+            width = self.width,
+            height = self.height
+        )
+    }
+}
+
+pub fn to_plist_string(sheet: &SheetData) -> String {
+    let frames: String = sheet
+        .sprites
+        .iter()
+        .map(|sd| sd.to_cocos2d_frame_string())
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+    <dict>
+        <key>frames</key>
+        <dict>
+{frames}        </dict>
+        <key>metadata</key>
+        <dict>
+            <key>format</key>
+            <integer>3</integer>
+            <key>pixelFormat</key>
+            <string>RGBA8888</string>
+            <key>premultiplyAlpha</key>
+            <false/>
+            <key>realTextureFileName</key>
+            <string>{image}</string>
+            <key>size</key>
+            <string>{{{width},{height}}}</string>
+            <key>textureFileName</key>
+            <string>{image}</string>
+        </dict>
+    </dict>
+</plist>
+",
+        image = escape_xml(&sheet.image_path()),
+        width = sheet.width,
+        height = sheet.height
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_data::tests::make_sheet;
+
+    #[test]
+    fn plist() {
+        let plist = to_plist_string(&make_sheet());
+        assert!(plist.contains(
+            "            <key>b</key>
+            <dict>
+                <key>aliases</key>
+                <array/>
+                <key>spriteOffset</key>
+                <string>{0,0}</string>
+                <key>spriteSize</key>
+                <string>{32,16}</string>
+                <key>spriteSourceSize</key>
+                <string>{32,16}</string>
+                <key>textureRect</key>
+                <string>{{36,2},{32,16}}</string>
+                <key>textureRotated</key>
+                <false/>
+            </dict>
+        </dict>
+"
+        ));
+        assert!(plist.contains(
+            "            <key>size</key>
+            <string>{70,40}</string>
+            <key>textureFileName</key>
+            <string>sheet.png</string>
+"
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+//! CSS classes, or SCSS mixins, showing each sprite as a background image
+
+use super::{file_name, SheetData, SpriteData};
+
+/// Class names only keep letters, digits, `-` and `_`, and are prefixed so they never start with a digit
+fn class_name(name: &str) -> String {
+    let name: String = file_name(name)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    "sprite-".to_owned() + &name
+}
+
+impl SpriteData {
+    fn to_css_declarations_string(&self, image_url: &str) -> String {
+        std::format!(
+            "    background-image: url(\"{image_url}\");
+    background-position: -{x}px -{y}px;
+    width: {width}px;
+    height: {height}px;
+",
+            x = self.x,
+            y = self.y,
+            width = self.width,
+            height = self.height
+        )
+    }
+}
+
+fn image_url(sheet: &SheetData) -> String {
+    sheet
+        .image_path()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+}
+
+pub fn to_css_string(sheet: &SheetData) -> String {
+    let image_url = image_url(sheet);
+    sheet
+        .sprites
+        .iter()
+        .map(|sd| {
+            format!(
+                ".{class} {{\n{declarations}}}\n",
+                class = class_name(&sd.name),
+                declarations = sd.to_css_declarations_string(&image_url)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// A mixin per sprite, plus a class including it
+pub fn to_scss_string(sheet: &SheetData) -> String {
+    let image_url = image_url(sheet);
+    sheet
+        .sprites
+        .iter()
+        .map(|sd| {
+            format!(
+                "@mixin {class} {{\n{declarations}}}\n\n.{class} {{\n    @include {class};\n}}\n",
+                class = class_name(&sd.name),
+                declarations = sd.to_css_declarations_string(&image_url)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_data::tests::make_sheet;
+
+    #[test]
+    fn sanitised_class_name() {
+        assert_eq!("sprite-a", class_name("a"));
+        assert_eq!("sprite-1_icon-big-png", class_name("icons/1_icon big.png"));
+    }
+
+    #[test]
+    fn css() {
+        let css = to_css_string(&make_sheet());
+        assert!(css.starts_with(
+            ".sprite-a {
+    background-image: url(\"sheet.png\");
+    background-position: -2px -2px;
+    width: 32px;
+    height: 32px;
+}
+
+.sprite-b {
+    background-image: url(\"sheet.png\");
+    background-position: -36px -2px;
+"
+        ));
+    }
+
+    #[test]
+    fn scss() {
+        let scss = to_scss_string(&make_sheet());
+        assert!(scss.starts_with(
+            "@mixin sprite-a {
+    background-image: url(\"sheet.png\");
+    background-position: -2px -2px;
+    width: 32px;
+    height: 32px;
+}
+
+.sprite-a {
+    @include sprite-a;
+}
+
+@mixin sprite-b {
+"
+        ));
+    }
+}
@@ -0,0 +1,211 @@
+//! Godot 4 text resources: an `AtlasTexture` per sprite and a `SpriteFrames` of the animations
+
+use std::path::{Path, PathBuf};
+
+use super::{file_extension, file_name, relative_path, MetaDataFile, SheetData, SpriteData};
+use crate::config::MetaDataFormat;
+
+/// Escapes text for a double quoted string in a Godot text resource
+fn escape_godot(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_ascii_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl SpriteData {
+    fn godot_resource_name(&self) -> String {
+        file_name(&self.name).replace(' ', "_") + file_extension(MetaDataFormat::Godot)
+    }
+
+    fn to_godot_atlas_texture_string(&self, image_path: &str) -> String {
+        std::format!(
+            "[gd_resource type=\"AtlasTexture\" load_steps=2 format=3]
+
+[ext_resource type=\"Texture2D\" path=\"{image_path}\" id=\"1\"]
+
+[resource]
+atlas = ExtResource(\"1\")
+region = Rect2({x}, {y}, {width}, {height})
+margin = Rect2(0, 0, 0, 0)
+",
+            image_path = escape_godot(image_path),
+            x = self.x,
+            y = self.y,
+            width = self.width,
+            height = self.height
+        )
+    }
+}
+
+fn to_sprite_frames_string(sheet: &SheetData, resource_dir: &str) -> String {
+    let animations = sheet.animations();
+    let mut ext_resources = String::new();
+    let mut animation_strings = vec![];
+    let mut id = 0;
+    for (name, frames) in &animations {
+        let mut frame_strings = vec![];
+        for (_, sd) in frames {
+            id += 1;
+            ext_resources += &format!(
+                "[ext_resource type=\"Texture2D\" path=\"{path}\" id=\"{id}\"]\n",
+                path = escape_godot(&format!("{resource_dir}/{}", sd.godot_resource_name()))
+            );
+            frame_strings.push(format!(
+                "{{\n\"duration\": 1.0,\n\"texture\": ExtResource(\"{id}\")\n}}"
+            ));
+        }
+        animation_strings.push(format!(
+            "{{\n\"frames\": [{frames}],\n\"loop\": true,\n\"name\": &\"{name}\",\n\"speed\": 5.0\n}}",
+            frames = frame_strings.join(", "),
+            name = escape_godot(&name.replace(' ', "_"))
+        ));
+    }
+    if !ext_resources.is_empty() {
+        ext_resources += "\n";
+    }
+    format!(
+        "[gd_resource type=\"SpriteFrames\" load_steps={load_steps} format=3]\n\n{ext_resources}[resource]\nanimations = [{animations}]\n",
+        load_steps = id + 1,
+        animations = animation_strings.join(", ")
+    )
+}
+
+/// The directory named after the `SpriteFrames`, next to it, that the `AtlasTexture`s go in
+pub fn resource_dir(sprite_frames_path: &str) -> PathBuf {
+    Path::new(sprite_frames_path).with_extension("")
+}
+
+/// Writes the `AtlasTexture`s into the resource directory
+pub fn to_resource_files(sheet: &SheetData) -> Vec<MetaDataFile> {
+    let sprite_frames_path = sheet.meta_data_path(file_extension(MetaDataFormat::Godot));
+    let resource_dir = resource_dir(&sprite_frames_path);
+    let image_path = relative_path(&resource_dir, Path::new(&sheet.output_file));
+    let resource_dir = resource_dir.to_string_lossy();
+    let mut files: Vec<MetaDataFile> = sheet
+        .sprites
+        .iter()
+        .map(|sd| MetaDataFile {
+            path: format!("{resource_dir}/{name}", name = sd.godot_resource_name()),
+            contents: sd.to_godot_atlas_texture_string(&image_path).into_bytes(),
+        })
+        .collect();
+    files.push(MetaDataFile {
+        path: sprite_frames_path,
+        contents: to_sprite_frames_string(sheet, file_name(&resource_dir)).into_bytes(),
+    });
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_data::tests::make_sheet;
+
+    #[test]
+    fn atlas_textures() {
+        let files = to_resource_files(&make_sheet());
+        assert_eq!(3, files.len());
+        assert_eq!("out/sheet/a.tres", files[0].path);
+        assert_eq!(
+            "[gd_resource type=\"AtlasTexture\" load_steps=2 format=3]
+
+[ext_resource type=\"Texture2D\" path=\"../sheet.png\" id=\"1\"]
+
+[resource]
+atlas = ExtResource(\"1\")
+region = Rect2(2, 2, 32, 32)
+margin = Rect2(0, 0, 0, 0)
+",
+            files[0].text()
+        );
+        assert_eq!("out/sheet/b.tres", files[1].path);
+    }
+
+    #[test]
+    fn custom_path() {
+        let mut sheet = make_sheet();
+        sheet.meta_data_file = Some("game/frames.tres".to_owned());
+        let files = to_resource_files(&sheet);
+        assert_eq!("game/frames/a.tres", files[0].path);
+        assert!(files[0].text().contains("path=\"../../out/sheet.png\""));
+        assert_eq!("game/frames.tres", files[2].path);
+    }
+
+    #[test]
+    fn dotted_output_paths() {
+        let mut sheet = make_sheet();
+        sheet.output_file = "./sheet.png".to_owned();
+        let files = to_resource_files(&sheet);
+        assert_eq!("./sheet/a.tres", files[0].path);
+        assert!(files[0].text().contains("path=\"../sheet.png\""));
+        assert_eq!("./sheet.tres", files[2].path);
+
+        sheet.output_file = "out.v2/sheet.png".to_owned();
+        let files = to_resource_files(&sheet);
+        assert_eq!("out.v2/sheet/a.tres", files[0].path);
+        assert!(files[0].text().contains("path=\"../sheet.png\""));
+        assert_eq!("out.v2/sheet.tres", files[2].path);
+    }
+
+    #[test]
+    fn sprite_frames() {
+        let mut sheet = make_sheet();
+        sheet.sprites[0].name = "dir/walk_1".to_owned();
+        sheet.sprites[1].name = "dir/walk_0".to_owned();
+        let files = to_resource_files(&sheet);
+        assert_eq!("out/sheet.tres", files[2].path);
+        assert_eq!(
+            "[gd_resource type=\"SpriteFrames\" load_steps=3 format=3]
+
+[ext_resource type=\"Texture2D\" path=\"sheet/walk_0.tres\" id=\"1\"]
+[ext_resource type=\"Texture2D\" path=\"sheet/walk_1.tres\" id=\"2\"]
+
+[resource]
+animations = [{
+\"frames\": [{
+\"duration\": 1.0,
+\"texture\": ExtResource(\"1\")
+}, {
+\"duration\": 1.0,
+\"texture\": ExtResource(\"2\")
+}],
+\"loop\": true,
+\"name\": &\"walk\",
+\"speed\": 5.0
+}]
+",
+            files[2].text()
+        );
+    }
+
+    #[test]
+    fn escaped_strings() {
+        let mut sheet = make_sheet();
+        sheet.output_file = "out/say \"hi\".png".to_owned();
+        sheet.sprites[0].name = "in/a\"b\\c_0".to_owned();
+        let files = to_resource_files(&sheet);
+        assert_eq!("out/say \"hi\"/a\"b\\c_0.tres", files[0].path);
+        assert!(files[0].text().contains("path=\"../say \\\"hi\\\".png\""));
+        let sprite_frames = files[2].text();
+        assert!(sprite_frames.contains("path=\"say \\\"hi\\\"/a\\\"b\\\\c_0.tres\""));
+        assert!(sprite_frames.contains("\"name\": &\"a\\\"b\\\\c\""));
+    }
+
+    #[test]
+    fn no_animations() {
+        let files = to_resource_files(&make_sheet());
+        assert_eq!(
+            "[gd_resource type=\"SpriteFrames\" load_steps=1 format=3]\n\n[resource]\nanimations = []\n",
+            files[2].text()
+        );
+    }
+}
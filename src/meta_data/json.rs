@@ -0,0 +1,171 @@
+//! corral's own json: a `meta` block describing the sheet, and its `sprites`
+//!
+//! MessagePack and CBOR are written from the same model, so they carry exactly the same fields
+
+use serde::Serialize;
+
+use super::{SheetData, SpriteData};
+use crate::config::MetaDataOptions;
+
+#[derive(Serialize)]
+pub struct Meta<'a> {
+    pub app: &'a str,
+    pub version: &'a str,
+    pub image: String,
+    pub format: &'a str,
+    pub width: u32,
+    pub height: u32,
+    pub padding: u8,
+    pub scale: u32,
+    pub hash: String,
+}
+
+#[derive(Serialize)]
+pub struct Uv {
+    pub u0: f64,
+    pub v0: f64,
+    pub u1: f64,
+    pub v1: f64,
+}
+
+#[derive(Serialize)]
+pub struct Sprite {
+    pub height: u32,
+    pub name: String,
+    pub width: u32,
+    pub x: u32,
+    pub y: u32,
+    #[serde(flatten)]
+    pub uv: Option<Uv>,
+}
+
+#[derive(Serialize)]
+pub struct Atlas<'a> {
+    pub meta: Meta<'a>,
+    pub sprites: Vec<Sprite>,
+}
+
+/// The atlas, or only its sprites when the bare array is asked for
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Document<'a> {
+    Atlas(Atlas<'a>),
+    Sprites(Vec<Sprite>),
+}
+
+impl SpriteData {
+    fn to_json_sprite(&self, uv: Option<(f64, f64, f64, f64)>) -> Sprite {
+        Sprite {
+            height: self.height,
+            name: self.name.replace(' ', "_"),
+            width: self.width,
+            x: self.x,
+            y: self.y,
+            uv: uv.map(|(u0, v0, u1, v1)| Uv { u0, v0, u1, v1 }),
+        }
+    }
+}
+
+pub fn to_meta(sheet: &SheetData) -> Meta<'static> {
+    Meta {
+        app: env!("CARGO_PKG_HOMEPAGE"),
+        version: env!("CARGO_PKG_VERSION"),
+        image: sheet.image_path(),
+        format: "RGBA8888",
+        width: sheet.width,
+        height: sheet.height,
+        padding: sheet.padding,
+        scale: 1,
+        hash: format!("{:016x}", sheet.image_hash),
+    }
+}
+
+pub fn to_sprites(sheet: &SheetData, options: &MetaDataOptions) -> Vec<Sprite> {
+    sheet
+        .sprites
+        .iter()
+        .map(|sd| sd.to_json_sprite(sheet.optional_uv(sd, options)))
+        .collect()
+}
+
+pub fn to_atlas<'a>(sheet: &SheetData, options: &MetaDataOptions) -> Atlas<'a> {
+    Atlas {
+        meta: to_meta(sheet),
+        sprites: to_sprites(sheet, options),
+    }
+}
+
+pub fn to_document<'a>(sheet: &SheetData, options: &MetaDataOptions) -> Document<'a> {
+    if options.json_array {
+        Document::Sprites(to_sprites(sheet, options))
+    } else {
+        Document::Atlas(to_atlas(sheet, options))
+    }
+}
+
+pub fn to_json_string(sheet: &SheetData, options: &MetaDataOptions) -> serde_json::Result<String> {
+    Ok(serde_json::to_string(&to_document(sheet, options))? + "\n")
+}
+
+/// Structs are written as maps keyed by field name, as in the json
+pub fn to_msgpack(
+    sheet: &SheetData,
+    options: &MetaDataOptions,
+) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    rmp_serde::to_vec_named(&to_document(sheet, options))
+}
+
+pub fn to_cbor(
+    sheet: &SheetData,
+    options: &MetaDataOptions,
+) -> Result<Vec<u8>, ciborium::ser::Error<std::io::Error>> {
+    let mut cbor = vec![];
+    ciborium::into_writer(&to_document(sheet, options), &mut cbor)?;
+    Ok(cbor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_data::tests::make_sheet;
+
+    #[test]
+    fn escaped_names() -> serde_json::Result<()> {
+        let options = MetaDataOptions {
+            json_array: true,
+            ..Default::default()
+        };
+        let mut sheet = make_sheet();
+        sheet.sprites.truncate(1);
+        sheet.sprites[0].name = "dir\\say \"hi\"".to_owned();
+        assert_eq!(
+            "[{\"height\":32,\"name\":\"dir\\\\say_\\\"hi\\\"\",\"width\":32,\"x\":2,\"y\":2}]\n",
+            to_json_string(&sheet, &options)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn same_model_as_json() -> Result<(), Box<dyn std::error::Error>> {
+        for options in [
+            MetaDataOptions::default(),
+            MetaDataOptions {
+                json_array: true,
+                uv: Some(crate::config::UvMode::Inset),
+                ..Default::default()
+            },
+        ] {
+            // Sizes that keep the uvs exact, as json is read back without full float precision
+            let mut sheet = make_sheet();
+            sheet.width = 128;
+            sheet.height = 64;
+            let json: serde_json::Value = serde_json::from_str(&to_json_string(&sheet, &options)?)?;
+            let msgpack: serde_json::Value = rmp_serde::from_slice(&to_msgpack(&sheet, &options)?)?;
+            let cbor: serde_json::Value =
+                ciborium::from_reader(to_cbor(&sheet, &options)?.as_slice())?;
+            assert_eq!(json, msgpack);
+            assert_eq!(json, cbor);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+//! libGDX TextureAtlas text format, in the layout written by libGDX's own packer
+
+use super::{SheetData, SpriteData};
+
+impl SpriteData {
+    fn to_libgdx_region_string(&self) -> String {
+        let (name, index) = self.frame_index();
+        let mut region = std::format!(
+            "{name}
+  rotate: false
+  xy: {x}, {y}
+  size: {width}, {height}
+",
+            name = name.replace(' ', "_"),
+            x = self.x,
+            y = self.y,
+            width = self.width,
+            height = self.height
+        );
+        if let Some(nine_patch) = self.nine_patch {
+            let [left, right, top, bottom] = nine_patch.split;
+            region += &format!("  split: {left}, {right}, {top}, {bottom}\n");
+            if let Some([left, right, top, bottom]) = nine_patch.pad {
+                region += &format!("  pad: {left}, {right}, {top}, {bottom}\n");
+            }
+        }
+        region
+            + &std::format!(
+                "  orig: {width}, {height}
+  offset: 0, 0
+  index: {index}
+",
+                width = self.width,
+                height = self.height,
+                index = index.map_or(-1, i64::from)
+            )
+    }
+}
+
+pub fn to_atlas_string(sheet: &SheetData) -> String {
+    let page = std::format!(
+        "
+{image}
+size: {width}, {height}
+format: RGBA8888
+filter: Nearest, Nearest
+repeat: none
+",
+        image = sheet.image_path(),
+        width = sheet.width,
+        height = sheet.height
+    );
+    sheet
+        .sprites
+        .iter()
+        .fold(page, |atlas, sd| atlas + &sd.to_libgdx_region_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_data::tests::make_sheet;
+    use crate::nine_patch::NinePatch;
+
+    #[test]
+    fn atlas() {
+        let mut sheet = make_sheet();
+        sheet.sprites[0].name = "walk_2".to_owned();
+        sheet.sprites[1].nine_patch = Some(NinePatch {
+            split: [4, 4, 2, 2],
+            pad: Some([1, 1, 0, 0]),
+        });
+        assert_eq!(
+            "
+sheet.png
+size: 70, 40
+format: RGBA8888
+filter: Nearest, Nearest
+repeat: none
+walk
+  rotate: false
+  xy: 2, 2
+  size: 32, 32
+  orig: 32, 32
+  offset: 0, 0
+  index: 2
+b
+  rotate: false
+  xy: 36, 2
+  size: 32, 16
+  split: 4, 4, 2, 2
+  pad: 1, 1, 0, 0
+  orig: 32, 16
+  offset: 0, 0
+  index: -1
+",
+            to_atlas_string(&sheet)
+        );
+    }
+}
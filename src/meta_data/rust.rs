@@ -0,0 +1,105 @@
+//! Rust source with a constant per sprite, so sprite names are checked at compile time
+
+use super::{SheetData, SpriteData};
+
+/// Items the generated module declares besides the sprite constants
+const RESERVED_NAMES: [&str; 4] = ["SHEET_IMAGE", "SHEET_WIDTH", "SHEET_HEIGHT", "SPRITES"];
+
+impl SpriteData {
+    fn to_rust_const_string(&self, identifier: &str, sheet: &SheetData) -> String {
+        // Printed as the f32 the generated code stores
+        let (u0, v0, u1, v1) = sheet.uv(self);
+        let (u0, v0, u1, v1) = (u0 as f32, v0 as f32, u1 as f32, v1 as f32);
+        std::format!(
+            "pub const {identifier}: Sprite = Sprite {{
+    name: {name:?},
+    x: {x},
+    y: {y},
+    width: {w},
+    height: {h},
+    pivot: (0.5, 0.5),
+    uv: ({u0:?}, {v0:?}, {u1:?}, {v1:?}),
+}};
+",
+            name = self.name,
+            x = self.x,
+            y = self.y,
+            w = self.width,
+            h = self.height,
+        )
+    }
+}
+
+pub fn to_module_string(sheet: &SheetData) -> String {
+    let identifiers = sheet.constant_identifiers(&RESERVED_NAMES);
+    let consts: String = sheet
+        .sprites
+        .iter()
+        .zip(&identifiers)
+        .map(|(sd, identifier)| sd.to_rust_const_string(identifier, sheet) + "\n")
+        .collect();
+    format!(
+        "// Generated by corral, do not edit
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {{
+    pub name: &'static str,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Relative to the sprite, (0.5, 0.5) is the centre
+    pub pivot: (f32, f32),
+    /// (u0, v0, u1, v1) from the top left of the sheet
+    pub uv: (f32, f32, f32, f32),
+}}
+
+pub const SHEET_IMAGE: &str = {image:?};
+pub const SHEET_WIDTH: u32 = {width};
+pub const SHEET_HEIGHT: u32 = {height};
+
+{consts}/// Every sprite, sorted by name
+pub const SPRITES: [Sprite; {count}] = [{identifiers}];
+
+pub fn sprite(name: &str) -> Option<&'static Sprite> {{
+    SPRITES
+        .binary_search_by_key(&name, |sprite| sprite.name)
+        .ok()
+        .map(|index| &SPRITES[index])
+}}
+",
+        image = sheet.image_path(),
+        width = sheet.width,
+        height = sheet.height,
+        count = identifiers.len(),
+        identifiers = identifiers.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_data::tests::make_sheet;
+
+    #[test]
+    fn module() {
+        let module = to_module_string(&make_sheet());
+        assert!(module.contains(
+            "pub const SHEET_IMAGE: &str = \"sheet.png\";
+pub const SHEET_WIDTH: u32 = 70;
+pub const SHEET_HEIGHT: u32 = 40;
+
+pub const A: Sprite = Sprite {
+    name: \"a\",
+    x: 2,
+    y: 2,
+    width: 32,
+    height: 32,
+    pivot: (0.5, 0.5),
+    uv: (0.028571429, 0.05, 0.4857143, 0.85),
+};
+"
+        ));
+        assert!(module.contains("pub const SPRITES: [Sprite; 2] = [A, B];\n"));
+    }
+}
@@ -0,0 +1,51 @@
+//! Starling/Sparrow `TextureAtlas` XML, also read by HaxeFlixel and OpenFL
+
+use super::{escape_xml, SheetData, SpriteData};
+
+impl SpriteData {
+    fn to_starling_string(&self) -> String {
+        std::format!(
+            "    <SubTexture name=\"{name}\" x=\"{x}\" y=\"{y}\" width=\"{width}\" height=\"{height}\" frameX=\"0\" frameY=\"0\" frameWidth=\"{width}\" frameHeight=\"{height}\" rotated=\"false\"/>",
+            name = escape_xml(&self.name.replace(' ', "_")),
+            x = self.x,
+            y = self.y,
+            width = self.width,
+            height = self.height
+        )
+    }
+}
+
+pub fn to_xml_string(sheet: &SheetData) -> String {
+    let sub_textures: String = sheet
+        .sprites
+        .iter()
+        .map(|sd| sd.to_starling_string() + "\n")
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<TextureAtlas imagePath=\"{image}\" width=\"{width}\" height=\"{height}\">\n{sub_textures}</TextureAtlas>\n",
+        image = escape_xml(&sheet.image_path()),
+        width = sheet.width,
+        height = sheet.height
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_data::tests::make_sheet;
+
+    #[test]
+    fn xml() {
+        let mut sheet = make_sheet();
+        sheet.sprites[1].name = "b&\"c\"".to_owned();
+        assert_eq!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<TextureAtlas imagePath=\"sheet.png\" width=\"70\" height=\"40\">
+    <SubTexture name=\"a\" x=\"2\" y=\"2\" width=\"32\" height=\"32\" frameX=\"0\" frameY=\"0\" frameWidth=\"32\" frameHeight=\"32\" rotated=\"false\"/>
+    <SubTexture name=\"b&amp;&quot;c&quot;\" x=\"36\" y=\"2\" width=\"32\" height=\"16\" frameX=\"0\" frameY=\"0\" frameWidth=\"32\" frameHeight=\"16\" rotated=\"false\"/>
+</TextureAtlas>
+",
+            to_xml_string(&sheet)
+        );
+    }
+}
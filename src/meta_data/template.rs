@@ -0,0 +1,276 @@
+//! User supplied Handlebars templates, rendered with the whole atlas as their data
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use handlebars::{Handlebars, Template};
+use serde::Serialize;
+
+use super::{file_extension, file_name, MetaDataFile, SheetData, SpriteData};
+use crate::config::{MetaDataFormat, MetaDataOptions};
+use crate::error::CorralError;
+use crate::nine_patch::NinePatch;
+
+#[derive(Serialize)]
+struct Page {
+    image: String,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Serialize)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+#[derive(Serialize)]
+struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Serialize)]
+struct Uv {
+    u0: f64,
+    v0: f64,
+    u1: f64,
+    v1: f64,
+}
+
+#[derive(Serialize)]
+struct Sprite<'a> {
+    name: &'a str,
+    page: usize,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    /// Relative to the sprite, 0.5 is the centre
+    pivot: Point,
+    /// Sprites are never trimmed yet, so the source rect always covers the whole sprite
+    trimmed: bool,
+    source: Rect,
+    source_width: u32,
+    source_height: u32,
+    uv: Uv,
+    nine_patch: Option<NinePatch>,
+    animation: Option<&'a str>,
+    frame: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct Animation<'a> {
+    name: &'a str,
+    /// Sprite names, in frame order
+    frames: Vec<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Atlas<'a> {
+    app: &'a str,
+    version: &'a str,
+    image: String,
+    width: u32,
+    height: u32,
+    pages: Vec<Page>,
+    sprites: Vec<Sprite<'a>>,
+    animations: Vec<Animation<'a>>,
+}
+
+impl SpriteData {
+    fn to_template_sprite(&self, sheet: &SheetData) -> Sprite<'_> {
+        let (u0, v0, u1, v1) = sheet.uv(self);
+        let (animation, frame) = match self.frame_index() {
+            // Named as in `animations`, which groups frames across directories
+            (animation, Some(frame)) => (Some(file_name(animation)), Some(frame)),
+            (_, None) => (None, None),
+        };
+        Sprite {
+            name: &self.name,
+            page: 0,
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+            pivot: Point { x: 0.5, y: 0.5 },
+            trimmed: false,
+            source: Rect {
+                x: 0,
+                y: 0,
+                width: self.width,
+                height: self.height,
+            },
+            source_width: self.width,
+            source_height: self.height,
+            uv: Uv { u0, v0, u1, v1 },
+            nine_patch: self.nine_patch,
+            animation,
+            frame,
+        }
+    }
+}
+
+fn to_atlas(sheet: &SheetData) -> Atlas<'_> {
+    Atlas {
+        app: env!("CARGO_PKG_HOMEPAGE"),
+        version: env!("CARGO_PKG_VERSION"),
+        image: sheet.image_path(),
+        width: sheet.width,
+        height: sheet.height,
+        pages: vec![Page {
+            image: sheet.image_path(),
+            width: sheet.width,
+            height: sheet.height,
+        }],
+        sprites: sheet
+            .sprites
+            .iter()
+            .map(|sd| sd.to_template_sprite(sheet))
+            .collect(),
+        animations: sheet
+            .animations()
+            .into_iter()
+            .map(|(name, frames)| Animation {
+                name,
+                frames: frames.iter().map(|(_, sd)| sd.name.as_str()).collect(),
+            })
+            .collect(),
+    }
+}
+
+/// A `--template` file, read and compiled while parsing the arguments so a missing or broken
+/// template stops corral before it writes anything
+#[derive(Clone)]
+pub struct TemplateFile {
+    pub path: String,
+    template: Template,
+}
+
+impl TemplateFile {
+    pub fn load(path: &str) -> Result<Self, CorralError> {
+        let source = fs::read_to_string(path).map_err(|err| CorralError::io(path, err))?;
+        let template = Template::compile(&source).map_err(|err| CorralError::Config {
+            message: format!("--template={path} isn't a valid Handlebars template, {err}"),
+        })?;
+        Ok(TemplateFile {
+            path: path.to_owned(),
+            template,
+        })
+    }
+}
+
+fn render(template: &Template, sheet: &SheetData) -> Result<String, Box<dyn Error>> {
+    let mut handlebars = Handlebars::new();
+    // Templates produce code and data rather than html
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars.register_template("atlas", template.clone());
+    Ok(handlebars.render("atlas", &to_atlas(sheet))?)
+}
+
+/// Takes the output extension from the template, so `sprites.lua.hbs` writes `<sheet>.lua`
+pub fn output_extension(template_file: &str) -> String {
+    let path = Path::new(template_file);
+    let path = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("hbs" | "handlebars") => Path::new(path.file_stem().unwrap_or_default()),
+        _ => path,
+    };
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => ".".to_owned() + extension,
+        None => file_extension(MetaDataFormat::Template).to_owned(),
+    }
+}
+
+pub fn to_template_file(
+    sheet: &SheetData,
+    options: &MetaDataOptions,
+) -> Result<Vec<MetaDataFile>, Box<dyn Error>> {
+    let template_file = options
+        .template
+        .as_ref()
+        .ok_or("no template file given, use --template=template_file")?;
+    Ok(vec![MetaDataFile {
+        path: sheet.meta_data_path(&output_extension(&template_file.path)),
+        contents: render(&template_file.template, sheet)?.into_bytes(),
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_data::tests::make_sheet;
+
+    #[test]
+    fn extension_from_template() {
+        assert_eq!(".lua", output_extension("templates/sprites.lua.hbs"));
+        assert_eq!(".lua", output_extension("sprites.lua.handlebars"));
+        assert_eq!(".lua", output_extension("sprites.lua"));
+        assert_eq!(".txt", output_extension("sprites.hbs"));
+    }
+
+    #[test]
+    fn render_sprites() -> Result<(), Box<dyn Error>> {
+        let template = "{{image}} {{width}}x{{height}}
+{{#each sprites}}{{name}} {{x}},{{y}} {{width}}x{{height}} {{uv.u0}} {{pivot.x}}{{#unless @last}}
+{{/unless}}{{/each}}";
+        assert_eq!(
+            "sheet.png 70x40
+a 2,2 32x32 0.02857142857142857 0.5
+b 36,2 32x16 0.5142857142857142 0.5",
+            render(&Template::compile(template)?, &make_sheet())?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn render_animations_unescaped() -> Result<(), Box<dyn Error>> {
+        let mut sheet = make_sheet();
+        sheet.sprites[0].name = "<walk>_0".to_owned();
+        sheet.sprites[1].name = "<walk>_1".to_owned();
+        let template = "{{#each animations}}{{name}}: {{#each frames}}{{this}} {{/each}}{{/each}}";
+        assert_eq!(
+            "<walk>: <walk>_0 <walk>_1 ",
+            render(&Template::compile(template)?, &sheet)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn animation_names_match() -> Result<(), Box<dyn Error>> {
+        let mut sheet = make_sheet();
+        sheet.sprites[0].name = "in/walk_0".to_owned();
+        sheet.sprites[1].name = "in/walk_1".to_owned();
+        let template =
+            "{{#each sprites}}{{animation}} {{/each}}{{#each animations}}{{name}}{{/each}}";
+        assert_eq!(
+            "walk walk walk",
+            render(&Template::compile(template)?, &sheet)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn load_template_file() -> Result<(), Box<dyn Error>> {
+        let dir = std::env::temp_dir().join("corral-load-template-file");
+        fs::create_dir_all(&dir)?;
+        let valid = dir.join("sprites.lua.hbs");
+        fs::write(&valid, "{{#each sprites}}{{name}}{{/each}}")?;
+        let broken = dir.join("broken.lua.hbs");
+        fs::write(&broken, "{{#each sprites}}{{name}}")?;
+
+        let template_file = TemplateFile::load(valid.to_str().unwrap())?;
+        assert_eq!("ab", render(&template_file.template, &make_sheet())?);
+        assert!(matches!(
+            TemplateFile::load(broken.to_str().unwrap()),
+            Err(CorralError::Config { .. })
+        ));
+        assert!(matches!(
+            TemplateFile::load(dir.join("missing.hbs").to_str().unwrap()),
+            Err(CorralError::Io { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+//! TexturePacker's JSON schema, as read by Phaser, PixiJS and friends
+
+use super::{quote_json, SheetData, SpriteData};
+
+impl SpriteData {
+    fn to_texture_packer_frame_string(&self) -> String {
+        std::format!(
+            "\"frame\":{{\"x\":{x},\"y\":{y},\"w\":{width},\"h\":{height}}},\
+             \"rotated\":false,\
+             \"trimmed\":false,\
+             \"spriteSourceSize\":{{\"x\":0,\"y\":0,\"w\":{width},\"h\":{height}}},\
+             \"sourceSize\":{{\"w\":{width},\"h\":{height}}}",
+            x = self.x,
+            y = self.y,
+            width = self.width,
+            height = self.height
+        )
+    }
+}
+
+fn to_meta_string(sheet: &SheetData) -> String {
+    std::format!(
+        "{{\"app\":\"{app}\",\"version\":\"{version}\",\"image\":{image},\"format\":\"RGBA8888\",\"size\":{{\"w\":{width},\"h\":{height}}},\"scale\":\"1\"}}",
+        app = env!("CARGO_PKG_HOMEPAGE"),
+        version = env!("CARGO_PKG_VERSION"),
+        image = quote_json(&sheet.image_path()),
+        width = sheet.width,
+        height = sheet.height
+    )
+}
+
+/// `frames` is an object keyed by sprite name
+pub fn to_hash_string(sheet: &SheetData) -> String {
+    let frames: String = sheet
+        .sprites
+        .iter()
+        .map(|sd| {
+            format!(
+                "{name}:{{{frame}}}",
+                name = quote_json(&sd.name.replace(' ', "_")),
+                frame = sd.to_texture_packer_frame_string()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+    format!(
+        "{{\"frames\":{{{frames}}},\"meta\":{meta}}}\n",
+        meta = to_meta_string(sheet)
+    )
+}
+
+/// `frames` is an array, each entry carrying its sprite name as `filename`
+pub fn to_array_string(sheet: &SheetData) -> String {
+    let frames: String = sheet
+        .sprites
+        .iter()
+        .map(|sd| {
+            format!(
+                "{{\"filename\":{name},{frame}}}",
+                name = quote_json(&sd.name.replace(' ', "_")),
+                frame = sd.to_texture_packer_frame_string()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+    format!(
+        "{{\"frames\":[{frames}],\"meta\":{meta}}}\n",
+        meta = to_meta_string(sheet)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_data::tests::make_sheet;
+
+    fn meta() -> String {
+        format!(
+            "\"meta\":{{\"app\":\"https://github.com/danielclarke/corral\",\"version\":\"{version}\",\"image\":\"sheet.png\",\"format\":\"RGBA8888\",\"size\":{{\"w\":70,\"h\":40}},\"scale\":\"1\"}}",
+            version = env!("CARGO_PKG_VERSION")
+        )
+    }
+
+    #[test]
+    fn hash() {
+        let expected = "{\"frames\":{\"a\":{\"frame\":{\"x\":2,\"y\":2,\"w\":32,\"h\":32},\"rotated\":false,\"trimmed\":false,\"spriteSourceSize\":{\"x\":0,\"y\":0,\"w\":32,\"h\":32},\"sourceSize\":{\"w\":32,\"h\":32}},\
+\"b\":{\"frame\":{\"x\":36,\"y\":2,\"w\":32,\"h\":16},\"rotated\":false,\"trimmed\":false,\"spriteSourceSize\":{\"x\":0,\"y\":0,\"w\":32,\"h\":16},\"sourceSize\":{\"w\":32,\"h\":16}}},"
+            .to_owned()
+            + &meta()
+            + "}\n";
+        assert_eq!(expected, to_hash_string(&make_sheet()));
+    }
+
+    #[test]
+    fn array() {
+        let output = to_array_string(&make_sheet());
+        assert!(output.starts_with("{\"frames\":[{\"filename\":\"a\",\"frame\":{\"x\":2,"));
+        assert!(output.contains("},{\"filename\":\"b\",\"frame\":{\"x\":36,"));
+        assert!(output.ends_with(&(meta() + "}\n")));
+    }
+}
@@ -0,0 +1,154 @@
+//! Unity `TextureImporter` settings, importing the sheet as sprites already sliced
+
+use super::{
+    file_extension, file_name, fnv1a, MetaDataFile, SheetData, SpriteData, FNV_OFFSET_BASIS,
+};
+use crate::config::MetaDataFormat;
+
+/// Hashed from the sheet's path, so the same sheet always gets the same asset guid, and sheets
+/// of the same name in different directories get different ones
+fn guid(text: &str) -> String {
+    format!(
+        "{:016x}{:016x}",
+        fnv1a(FNV_OFFSET_BASIS, text.as_bytes()),
+        fnv1a(0x6c62_272e_07bb_0142, text.as_bytes())
+    )
+}
+
+fn quote_yaml(text: &str) -> String {
+    format!("'{text}'", text = text.replace('\'', "''"))
+}
+
+impl SpriteData {
+    /// Unity measures rects from the bottom left of the texture
+    fn to_unity_sprite_string(&self, sheet_height: u32) -> String {
+        // Unity borders are left, bottom, right, top
+        let [left, right, top, bottom] = self.nine_patch.map_or([0; 4], |np| np.split);
+        std::format!(
+            "    - serializedVersion: 2
+      name: {name}
+      rect:
+        serializedVersion: 2
+        x: {x}
+        y: {y}
+        width: {width}
+        height: {height}
+      alignment: 0
+      pivot: {{x: 0.5, y: 0.5}}
+      border: {{x: {left}, y: {bottom}, z: {right}, w: {top}}}
+",
+            name = quote_yaml(&file_name(&self.name).replace(' ', "_")),
+            x = self.x,
+            y = sheet_height - self.y - self.height,
+            width = self.width,
+            height = self.height
+        )
+    }
+}
+
+/// Written as `<sheet>.png.meta`, which Unity picks up when the sheet is added to a project
+pub fn to_meta_file(sheet: &SheetData) -> MetaDataFile {
+    let sprites: String = sheet
+        .sprites
+        .iter()
+        .map(|sd| sd.to_unity_sprite_string(sheet.height))
+        .collect();
+    let contents = format!(
+        "fileFormatVersion: 2
+guid: {guid}
+TextureImporter:
+  serializedVersion: 12
+  mipmaps:
+    enableMipMap: 0
+  textureType: 8
+  textureShape: 1
+  spriteMode: 2
+  spritePixelsToUnits: 100
+  alphaIsTransparency: 1
+  spriteSheet:
+    serializedVersion: 2
+    sprites:
+{sprites}  userData:
+  assetBundleName:
+  assetBundleVariant:
+",
+        guid = guid(&sheet.output_file)
+    );
+    MetaDataFile {
+        path: meta_file_path(&sheet.output_file, sheet.meta_data_file.as_deref()),
+        contents: contents.into_bytes(),
+    }
+}
+
+/// Unity looks for the `.meta` next to the image, named after the whole file name
+pub fn meta_file_path(output_file: &str, meta_data_file: Option<&str>) -> String {
+    match meta_data_file {
+        Some(path) => path.to_owned(),
+        None => output_file.to_owned() + file_extension(MetaDataFormat::Unity),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_data::tests::make_sheet;
+    use crate::nine_patch::NinePatch;
+
+    #[test]
+    fn stable_guid() {
+        assert_eq!(32, guid("sheet.png").len());
+        assert_eq!(guid("sheet.png"), guid("sheet.png"));
+        assert_ne!(guid("sheet.png"), guid("other.png"));
+
+        let guid_line = |output_file: &str| {
+            let mut sheet = make_sheet();
+            sheet.output_file = output_file.to_owned();
+            let file = to_meta_file(&sheet);
+            file.text().lines().nth(1).unwrap().to_owned()
+        };
+        assert_eq!(guid_line("ui/sheet.png"), guid_line("ui/sheet.png"));
+        assert_ne!(guid_line("ui/sheet.png"), guid_line("chars/sheet.png"));
+    }
+
+    #[test]
+    fn meta_file() {
+        let mut sheet = make_sheet();
+        sheet.sprites[0].name = "dir/it's".to_owned();
+        sheet.sprites[1].nine_patch = Some(NinePatch {
+            split: [1, 2, 3, 4],
+            pad: None,
+        });
+        let file = to_meta_file(&sheet);
+        assert_eq!("out/sheet.png.meta", file.path);
+        assert!(file.text().starts_with(&format!(
+            "fileFormatVersion: 2\nguid: {guid}\nTextureImporter:\n",
+            guid = guid("out/sheet.png")
+        )));
+        assert!(file.text().contains(
+            "    - serializedVersion: 2
+      name: 'it''s'
+      rect:
+        serializedVersion: 2
+        x: 2
+        y: 6
+        width: 32
+        height: 32
+      alignment: 0
+      pivot: {x: 0.5, y: 0.5}
+      border: {x: 0, y: 0, z: 0, w: 0}
+    - serializedVersion: 2
+      name: 'b'
+      rect:
+        serializedVersion: 2
+        x: 36
+        y: 22
+        width: 32
+        height: 16
+      alignment: 0
+      pivot: {x: 0.5, y: 0.5}
+      border: {x: 1, y: 4, z: 2, w: 3}
+  userData:
+"
+        ));
+    }
+}
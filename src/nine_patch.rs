@@ -0,0 +1,145 @@
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+
+/// Stretchable region of a `.9.png` image, in the Android/libGDX convention:
+/// distances from the left, right, top and bottom edges of the content
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct NinePatch {
+    pub split: [u32; 4],
+    pub pad: Option<[u32; 4]>,
+}
+
+pub fn is_nine_patch_file(path: &str) -> bool {
+    path.ends_with(".9.png")
+}
+
+fn is_marker(p: image::Rgba<u8>) -> bool {
+    p == image::Rgba([0, 0, 0, 255])
+}
+
+/// First and one past the last marker position along a border, relative to the content
+fn marker_span(markers: Vec<bool>) -> Option<(u32, u32)> {
+    let start = markers.iter().position(|m| *m)?;
+    let end = markers.iter().rposition(|m| *m)? + 1;
+    Some((start as u32, end as u32))
+}
+
+/// Reads the 1px marker border of a nine-patch and returns the image without it
+pub fn strip(img: DynamicImage) -> Result<(DynamicImage, NinePatch), &'static str> {
+    let (width, height) = img.dimensions();
+    if width < 3 || height < 3 {
+        return Err("nine-patch image is too small to contain a marker border");
+    }
+    let (content_width, content_height) = (width - 2, height - 2);
+
+    let top = (1..width - 1)
+        .map(|x| is_marker(img.get_pixel(x, 0)))
+        .collect();
+    let left = (1..height - 1)
+        .map(|y| is_marker(img.get_pixel(0, y)))
+        .collect();
+    let bottom = (1..width - 1)
+        .map(|x| is_marker(img.get_pixel(x, height - 1)))
+        .collect();
+    let right = (1..height - 1)
+        .map(|y| is_marker(img.get_pixel(width - 1, y)))
+        .collect();
+
+    let (split_x, split_y) = match (marker_span(top), marker_span(left)) {
+        (Some(split_x), Some(split_y)) => (split_x, split_y),
+        _ => return Err("nine-patch image is missing its top or left stretch markers"),
+    };
+    let pad = match (marker_span(bottom), marker_span(right)) {
+        (Some(pad_x), Some(pad_y)) => Some([
+            pad_x.0,
+            content_width - pad_x.1,
+            pad_y.0,
+            content_height - pad_y.1,
+        ]),
+        _ => None,
+    };
+
+    Ok((
+        img.crop_imm(1, 1, content_width, content_height),
+        NinePatch {
+            split: [
+                split_x.0,
+                content_width - split_x.1,
+                split_y.0,
+                content_height - split_y.1,
+            ],
+            pad,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: image::Rgba<u8> = image::Rgba([0, 0, 0, 255]);
+    const WHITE: image::Rgba<u8> = image::Rgba([255, 255, 255, 255]);
+
+    fn make_nine_patch(with_pad: bool) -> DynamicImage {
+        // 8x6 content inside a 10x8 image
+        let mut img = image::RgbaImage::from_pixel(10, 8, WHITE);
+        for x in 0..10 {
+            img.put_pixel(x, 0, image::Rgba([0, 0, 0, 0]));
+            img.put_pixel(x, 7, image::Rgba([0, 0, 0, 0]));
+        }
+        for y in 0..8 {
+            img.put_pixel(0, y, image::Rgba([0, 0, 0, 0]));
+            img.put_pixel(9, y, image::Rgba([0, 0, 0, 0]));
+        }
+        // stretch columns 2..5 and rows 1..3 of the content
+        for x in 3..6 {
+            img.put_pixel(x, 0, BLACK);
+        }
+        for y in 2..4 {
+            img.put_pixel(0, y, BLACK);
+        }
+        if with_pad {
+            for x in 2..8 {
+                img.put_pixel(x, 7, BLACK);
+            }
+            for y in 1..7 {
+                img.put_pixel(9, y, BLACK);
+            }
+        }
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn nine_patch_file() {
+        assert!(is_nine_patch_file("ui/button.9.png"));
+        assert!(!is_nine_patch_file("ui/button.png"));
+    }
+
+    #[test]
+    fn strip_split() -> Result<(), &'static str> {
+        let (img, nine_patch) = strip(make_nine_patch(false))?;
+        assert_eq!((8, 6), img.dimensions());
+        assert_eq!(WHITE, img.get_pixel(0, 0));
+        assert_eq!(
+            NinePatch {
+                split: [2, 3, 1, 3],
+                pad: None,
+            },
+            nine_patch
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn strip_split_and_pad() -> Result<(), &'static str> {
+        let (_, nine_patch) = strip(make_nine_patch(true))?;
+        assert_eq!(Some([1, 1, 0, 0]), nine_patch.pad);
+        Ok(())
+    }
+
+    #[test]
+    fn strip_without_markers() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+        assert!(strip(img).is_err());
+    }
+}
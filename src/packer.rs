@@ -1,93 +1,171 @@
-use std::error::Error;
+use std::fmt;
 use std::fs;
-use std::io::Write;
+use std::io::{self, Cursor, Write};
+use std::path::Path;
 
+use crate::bounding_box::BoundingBox;
+use crate::cache::{self, Cache};
 use crate::config::Config;
-use crate::config::MetaDataFormat;
-use crate::tree2d::{DataSize, Tree2d};
-use image::{DynamicImage, GenericImageView, ImageEncoder};
+use crate::error::CorralError;
+use crate::meta_data::{self, MetaDataFile, SheetData, SpriteData};
+use crate::nine_patch::{self, NinePatch};
+use crate::tree2d::{DataSize, Packer, Tree2d};
+use image::{ColorType, DynamicImage, ImageEncoder, ImageError, ImageFormat, RgbaImage};
+use rayon::prelude::*;
 
 struct NamedDynamicImage {
     name: String,
     img: DynamicImage,
+    nine_patch: Option<NinePatch>,
 }
 
-struct PackedImage {
-    img: DynamicImage,
-    meta_data: String,
+/// Collects images in memory and packs them into one sheet
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let atlas = corral::AtlasBuilder::new()
+///     .padding(1)
+///     .add_image("player", image::open("player.png")?)
+///     .add_image("enemy", image::open("enemy.png")?)
+///     .build()?;
+/// for sprite in &atlas.sprites {
+///     println!("{} at {}, {}", sprite.name, sprite.x, sprite.y);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct AtlasBuilder {
+    images: Vec<NamedDynamicImage>,
+    padding: u8,
 }
 
-impl PackedImage {
-    fn write(
-        &self,
-        output_file: &str,
-        output_file_format: MetaDataFormat,
-    ) -> Result<(), Box<dyn Error>> {
-        let buf = fs::File::create(output_file)?;
-        let encoder = image::codecs::png::PngEncoder::new_with_quality(
-            buf,
-            image::codecs::png::CompressionType::Best,
-            image::codecs::png::FilterType::Adaptive,
-        );
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        AtlasBuilder {
+            images: vec![],
+            padding: 2,
+        }
+    }
+}
 
-        encoder.write_image(
-            self.img.as_bytes(),
-            self.img.width(),
-            self.img.height(),
-            self.img.color(),
-        )?;
+impl AtlasBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        let extension = match output_file_format {
-            MetaDataFormat::Json => ".json",
-            MetaDataFormat::Lua => ".lua",
-        };
+    /// Space left around each sprite, filled by extruding its edges, 2 by default
+    pub fn padding(mut self, padding: u8) -> Self {
+        self.padding = padding;
+        self
+    }
 
-        let json_file = output_file.split('.').collect::<Vec<&str>>()[0].to_owned() + extension;
-        let mut buf = fs::File::create(&json_file)?;
-        match buf.write_all(self.meta_data.as_bytes()) {
-            Ok(..) => Ok(()),
-            Err(e) => Result::Err(Box::new(e)),
-        }
+    pub fn add_image(mut self, name: &str, img: DynamicImage) -> Self {
+        self.images.push(NamedDynamicImage {
+            name: name.to_owned(),
+            img,
+            nine_patch: None,
+        });
+        self
+    }
+
+    /// Adds an image with nine-patch guides in its outer pixels, which are stripped off
+    pub fn add_nine_patch_image(
+        mut self,
+        name: &str,
+        img: DynamicImage,
+    ) -> Result<Self, CorralError> {
+        let (img, nine_patch) = nine_patch::strip(img).map_err(|message| CorralError::Decode {
+            path: name.to_owned(),
+            message: message.to_owned(),
+        })?;
+        self.images.push(NamedDynamicImage {
+            name: name.to_owned(),
+            img,
+            nine_patch: Some(nine_patch),
+        });
+        Ok(self)
+    }
+
+    /// Adds every image in `input_dir`, named by their path without the extension, as the
+    /// `corral` command does
+    pub fn add_dir(mut self, input_dir: &str) -> Result<Self, CorralError> {
+        self.images.extend(load_dir(input_dir)?);
+        Ok(self)
+    }
+
+    pub fn build(self) -> Result<Atlas, CorralError> {
+        pack(self.padding, ImageCollection::new(self.images))
     }
 }
 
-struct SpriteData {
-    name: String,
-    x: u32,
-    y: u32,
-    width: u32,
-    height: u32,
+/// The packed sheet image and where each sprite went in it
+pub struct Atlas {
+    pub image: RgbaImage,
+    /// Sorted by name
+    pub sprites: Vec<SpriteData>,
+    pub padding: u8,
 }
 
-impl SpriteData {
-    fn to_lua_string(&self) -> String {
-        std::format!(
-            "    {name} = {{
-        x = {x},
-        y = {y},
-        width = {width},
-        height = {height},
-    }}",
-            name = self.name.replace(' ', "_").to_uppercase(),
-            x = self.x,
-            y = self.y,
-            width = self.width,
-            height = self.height
+impl Atlas {
+    /// For writing metadata, with `output_file` being where the sheet image is saved
+    pub(crate) fn into_sheet_data(self, output_file: &str) -> SheetData {
+        SheetData::new(
+            output_file,
+            self.image.width(),
+            self.image.height(),
+            self.padding,
+            meta_data::fnv1a(meta_data::FNV_OFFSET_BASIS, self.image.as_raw()),
+            self.sprites,
         )
     }
 
-    fn to_json_string(&self) -> String {
-        std::format!(
-            "{{\"height\":{height},\"name\":\"{name}\",\"width\":{width},\"x\":{x},\"y\":{y}}}",
-            name = self.name.replace(' ', "_"),
-            x = self.x,
-            y = self.y,
-            width = self.width,
-            height = self.height
-        )
+    /// Writes the sheet and its metadata, returning the hash of the sheet's png file along with
+    /// the metadata written
+    fn write(self, config: &Config) -> Result<(u64, SheetData), CorralError> {
+        let output_error = |err| CorralError::io(&config.output_file, err);
+        let mut png = vec![];
+        let encoder = image::codecs::png::PngEncoder::new_with_quality(
+            &mut png,
+            image::codecs::png::CompressionType::Best,
+            image::codecs::png::FilterType::Adaptive,
+        );
+
+        encoder
+            .write_image(
+                self.image.as_raw(),
+                self.image.width(),
+                self.image.height(),
+                ColorType::Rgba8,
+            )
+            .map_err(|err| match err {
+                ImageError::IoError(err) => output_error(err),
+                err => output_error(io::Error::other(err)),
+            })?;
+        fs::write(&config.output_file, &png).map_err(output_error)?;
+
+        let mut sheet_data = self.into_sheet_data(&config.output_file);
+        write_meta_data(&mut sheet_data, config)?;
+        Ok((cache::hash(&png), sheet_data))
     }
 }
 
+fn write_meta_data(sheet_data: &mut SheetData, config: &Config) -> Result<(), CorralError> {
+    // Every format is written from the same packing, so they all describe the same layout
+    for output in &config.meta_data_outputs {
+        sheet_data.meta_data_file = output.path.clone();
+        let meta_data = sheet_data.to_meta_data_files(output.format, &config.meta_data_options)?;
+        for MetaDataFile { path, contents } in &meta_data {
+            if let Some(parent) = Path::new(path).parent() {
+                fs::create_dir_all(parent).map_err(|err| CorralError::io(path, err))?;
+            }
+            fs::File::create(path)
+                .and_then(|mut buf| buf.write_all(contents))
+                .map_err(|err| CorralError::io(path, err))?;
+        }
+    }
+    Ok(())
+}
+
 #[allow(dead_code)]
 struct ImageCollection {
     named_images: Vec<NamedDynamicImage>,
@@ -100,7 +178,7 @@ impl ImageCollection {
     fn new(mut named_images: Vec<NamedDynamicImage>) -> ImageCollection {
         let mut max_width = 0u32;
         let mut max_height = 0u32;
-        for NamedDynamicImage { name: _, img } in &named_images {
+        for NamedDynamicImage { img, .. } in &named_images {
             max_width = max_width.max(img.width());
             max_height = max_height.max(img.height());
         }
@@ -119,56 +197,375 @@ impl ImageCollection {
     }
 }
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let img_collection = load_all(&config.input_dir)?;
-    let packed_img = pack(
-        &config.output_file,
-        config.output_file_format,
-        config.padding,
-        img_collection,
-    )?;
-    packed_img.write(&config.output_file, config.output_file_format)?;
-    Ok(())
+/// Packs the images in `config.input_dir`, writing the sheet and its metadata
+///
+/// Unless `config.cache` is off, the packing is remembered next to the sheet. When the next run
+/// finds the same files at the same sizes, only those whose contents changed are decoded, and
+/// they're redrawn in place.
+///
+/// With `config.stable`, sprites also keep their positions when others are added, removed or
+/// resized, and the new ones are placed in the space around them.
+pub fn run(config: Config) -> Result<Packing, CorralError> {
+    let inputs = read_dir(&config.input_dir)?;
+    let cache_path = Cache::path(&config.output_file);
+    let previous = if config.cache {
+        Cache::load(&cache_path)
+    } else {
+        None
+    };
+    let redrawn = match &previous {
+        Some(previous) => redraw(&config, previous, &inputs)?,
+        None => None,
+    };
+    let (packing, sheet_data, sheet_hash) = match redrawn {
+        Some((changed, sheet_data, sheet_hash)) => {
+            (Packing::Redrawn { changed }, sheet_data, sheet_hash)
+        }
+        None => {
+            let (atlas, packing) = match (&previous, config.stable) {
+                (_, false) => (pack_inputs(config.padding, &inputs)?, Packing::Full),
+                (None, true) => (
+                    pack_inputs(config.padding, &inputs)?,
+                    Packing::Repacked {
+                        reason: "there was no previous packing to keep",
+                    },
+                ),
+                (Some(previous), true) => match pack_stable(&config, previous, &inputs)? {
+                    Ok(stable) => stable,
+                    Err(reason) => (
+                        pack_inputs(config.padding, &inputs)?,
+                        Packing::Repacked { reason },
+                    ),
+                },
+            };
+            let (sheet_hash, sheet_data) = atlas.write(&config)?;
+            (packing, sheet_data, sheet_hash)
+        }
+    };
+    if config.cache {
+        let inputs: Vec<(&str, &str, u64)> = inputs
+            .iter()
+            .map(|input| (input.path.as_str(), sprite_name(&input.path), input.hash))
+            .collect();
+        if let Some(cache) = Cache::new(&sheet_data, sheet_hash, &inputs) {
+            cache.save(&cache_path)?;
+        }
+    }
+    Ok(packing)
 }
 
-fn load_all(input_dir: &str) -> Result<ImageCollection, Box<dyn Error>> {
-    let mut images = Vec::new();
+/// How `run` came by the sheet it wrote
+#[derive(Debug, PartialEq, Eq)]
+pub enum Packing {
+    /// Every sprite was packed from scratch
+    Full,
+    /// The previous packing was reused, with these sprites' new pixels redrawn in place
+    Redrawn { changed: Vec<String> },
+    /// Sprites were kept where they were, and new and resized ones were placed around them
+    Stable {
+        added: Vec<String>,
+        resized: Vec<String>,
+    },
+    /// Stable packing was asked for, but every sprite was packed from scratch, for this reason
+    Repacked { reason: &'static str },
+}
+
+impl fmt::Display for Packing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Packing::Full => write!(f, "Packed every sprite"),
+            Packing::Redrawn { changed } if changed.is_empty() => {
+                write!(f, "Nothing changed, kept the previous sheet")
+            }
+            Packing::Redrawn { changed } => write!(f, "Redrew {}", changed.join(", ")),
+            Packing::Stable { added, resized } if added.is_empty() && resized.is_empty() => {
+                write!(f, "Kept every sprite's position")
+            }
+            Packing::Stable { added, resized } => {
+                let mut placed = vec![];
+                if !added.is_empty() {
+                    placed.push(format!("new {}", added.join(", ")));
+                }
+                if !resized.is_empty() {
+                    placed.push(format!("resized {}", resized.join(", ")));
+                }
+                write!(
+                    f,
+                    "Kept previous positions, placed {}",
+                    placed.join(" and ")
+                )
+            }
+            Packing::Repacked { reason } => write!(f, "Repacked every sprite, as {reason}"),
+        }
+    }
+}
+
+fn pack_inputs(padding: u8, inputs: &[InputFile]) -> Result<Atlas, CorralError> {
+    let images = inputs.par_iter().map(decode).collect::<Result<_, _>>()?;
+    pack(padding, ImageCollection::new(images))
+}
+
+/// Packs with each sprite still there at the same size kept where `previous` put it, returning
+/// the names of those placed anew
+///
+/// The inner `Err` is why the previous positions couldn't be kept, and a full packing is needed.
+fn pack_stable(
+    config: &Config,
+    previous: &Cache,
+    inputs: &[InputFile],
+) -> Result<Result<(Atlas, Packing), &'static str>, CorralError> {
+    if previous.padding != config.padding {
+        return Ok(Err("the padding changed"));
+    }
+    let images: Vec<NamedDynamicImage> = inputs.par_iter().map(decode).collect::<Result<_, _>>()?;
+    let cached = previous.sprites_by_path();
+    let padding = config.padding as u32;
+    let mut kept = vec![];
+    let mut unplaced = vec![];
+    let mut resized = vec![];
+    for (input, named_img) in inputs.iter().zip(images) {
+        let (width, height) = (named_img.img.width(), named_img.img.height());
+        match cached.get(input.path.as_str()) {
+            Some(sprite) if (sprite.width, sprite.height) == (width, height) => {
+                let bb = BoundingBox {
+                    x: sprite.x.saturating_sub(padding),
+                    y: sprite.y.saturating_sub(padding),
+                    width: width + padding * 2,
+                    height: height + padding * 2,
+                };
+                kept.push((bb, named_img));
+            }
+            Some(_) => {
+                resized.push(named_img.name.clone());
+                unplaced.push(named_img);
+            }
+            None => unplaced.push(named_img),
+        }
+    }
+
+    let mut tree = match Tree2d::seeded(kept.iter().map(|(bb, img)| (*bb, img)).collect()) {
+        Ok(tree) => tree,
+        Err(_) => return Ok(Err("the previous positions couldn't all be kept")),
+    };
+    let unplaced = ImageCollection::new(unplaced);
+    place(&mut tree, config.padding, &unplaced.named_images)?;
+    let added = unplaced
+        .named_images
+        .iter()
+        .map(|named_img| named_img.name.clone())
+        .filter(|name| !resized.contains(name))
+        .collect();
+    resized.sort();
+    Ok(Ok((
+        draw(&tree, config.padding),
+        Packing::Stable { added, resized },
+    )))
+}
+
+/// Reuses the previous packing, decoding and redrawing only the sprites whose files changed
+///
+/// `None` when a full packing is needed instead: files were added, removed or resized, the
+/// padding changed, or the sheet is no longer the one the cache was written alongside.
+fn redraw(
+    config: &Config,
+    previous: &Cache,
+    inputs: &[InputFile],
+) -> Result<Option<(Vec<String>, SheetData, u64)>, CorralError> {
+    if previous.padding != config.padding || previous.sprites.len() != inputs.len() {
+        return Ok(None);
+    }
+    let png = match fs::read(&config.output_file) {
+        Ok(png) if cache::hash(&png) == previous.sheet_hash => png,
+        _ => return Ok(None),
+    };
+    let cached = previous.sprites_by_path();
+    let mut changed = vec![];
+    for input in inputs {
+        match cached.get(input.path.as_str()) {
+            Some(sprite) if sprite.hash == input.hash => (),
+            Some(sprite) => changed.push((input, *sprite)),
+            None => return Ok(None),
+        }
+    }
+
+    if changed.is_empty() {
+        let mut sheet_data = previous.to_sheet_data(&config.output_file);
+        write_meta_data(&mut sheet_data, config)?;
+        return Ok(Some((vec![], sheet_data, previous.sheet_hash)));
+    }
+
+    let mut image = match image::load_from_memory_with_format(&png, ImageFormat::Png) {
+        Ok(image) => image.into_rgba8(),
+        Err(_) => return Ok(None),
+    };
+    if (image.width(), image.height()) != (previous.width, previous.height) {
+        return Ok(None);
+    }
+    let decoded: Vec<NamedDynamicImage> = changed
+        .par_iter()
+        .map(|(input, _)| decode(input))
+        .collect::<Result<_, _>>()?;
+
+    let mut sprites: Vec<SpriteData> = previous
+        .sprites
+        .iter()
+        .map(|sprite| sprite.to_sprite_data())
+        .collect();
+    for ((_, sprite), named_img) in changed.iter().zip(&decoded) {
+        if (named_img.img.width(), named_img.img.height()) != (sprite.width, sprite.height) {
+            return Ok(None);
+        }
+    }
+    for ((_, sprite), named_img) in changed.iter().zip(&decoded) {
+        let padding = previous.padding as u32;
+        match named_img.img.as_rgba8() {
+            Some(src) => blit_extruded(&mut image, src, sprite.x, sprite.y, padding),
+            None => blit_extruded(
+                &mut image,
+                &named_img.img.to_rgba8(),
+                sprite.x,
+                sprite.y,
+                padding,
+            ),
+        }
+        if let Some(sd) = sprites.iter_mut().find(|sd| sd.name == sprite.name) {
+            sd.nine_patch = named_img.nine_patch;
+        }
+    }
+    sprites.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let atlas = Atlas {
+        image,
+        sprites,
+        padding: previous.padding,
+    };
+    let (sheet_hash, sheet_data) = atlas.write(config)?;
+    let changed = decoded
+        .into_iter()
+        .map(|named_img| named_img.name)
+        .collect();
+    Ok(Some((changed, sheet_data, sheet_hash)))
+}
 
-    let paths = fs::read_dir(input_dir)?;
+/// An input file read into memory, not yet decoded
+struct InputFile {
+    path: String,
+    contents: Vec<u8>,
+    hash: u64,
+}
 
-    for path in paths {
-        let path = path?.path();
+/// Reads every file in `input_dir`, several at a time
+fn read_dir(input_dir: &str) -> Result<Vec<InputFile>, CorralError> {
+    let mut paths = Vec::new();
+    for path in fs::read_dir(input_dir).map_err(|err| CorralError::io(input_dir, err))? {
+        let path = path.map_err(|err| CorralError::io(input_dir, err))?.path();
         if let Some(path_str) = path.to_str() {
-            match image::io::Reader::open(path_str) {
-                Ok(reader) => match reader.decode() {
-                    Ok(img) => {
-                        let name = path_str.split('.').next().unwrap().to_string();
-                        images.push(NamedDynamicImage { name, img });
-                    }
-                    Err(err) => {
-                        eprintln!("Error decoding {path_str}");
-                        return Err(Box::new(err));
-                    }
-                },
-                Err(err) => {
-                    eprintln!("Error opening {path_str}");
-                    return Err(Box::new(err));
+            paths.push(path_str.to_owned());
+        }
+    }
+    paths
+        .into_par_iter()
+        .map(|path| match fs::read(&path) {
+            Ok(contents) => Ok(InputFile {
+                hash: cache::hash(&contents),
+                path,
+                contents,
+            }),
+            Err(err) => Err(CorralError::io(&path, err)),
+        })
+        .collect()
+}
+
+/// Decodes every image in `input_dir`, several at a time
+fn load_dir(input_dir: &str) -> Result<Vec<NamedDynamicImage>, CorralError> {
+    read_dir(input_dir)?.par_iter().map(decode).collect()
+}
+
+/// The path without its extension
+fn sprite_name(path: &str) -> &str {
+    path.split('.').next().unwrap()
+}
+
+fn decode(input: &InputFile) -> Result<NamedDynamicImage, CorralError> {
+    let decode_error = |message: String| CorralError::Decode {
+        path: input.path.clone(),
+        message,
+    };
+    let mut reader = image::io::Reader::new(Cursor::new(&input.contents));
+    if let Ok(format) = ImageFormat::from_path(&input.path) {
+        reader.set_format(format);
+    }
+    match reader.decode() {
+        Ok(img) => {
+            let (img, nine_patch) = if nine_patch::is_nine_patch_file(&input.path) {
+                match nine_patch::strip(img) {
+                    Ok((img, nine_patch)) => (img, Some(nine_patch)),
+                    Err(err) => return Err(decode_error(err.to_owned())),
                 }
-            }
+            } else {
+                (img, None)
+            };
+            Ok(NamedDynamicImage {
+                name: sprite_name(&input.path).to_owned(),
+                img,
+                nine_patch,
+            })
+        }
+        Err(err) => Err(decode_error(err.to_string())),
+    }
+}
+
+/// Copies `src` into `target` with its top left at (`x`, `y`), then extrudes its edge pixels
+/// `padding` pixels outwards, a row at a time
+fn blit_extruded(target: &mut RgbaImage, src: &RgbaImage, x: u32, y: u32, padding: u32) {
+    let (width, height) = (src.width() as usize, src.height() as usize);
+    if width == 0 || height == 0 {
+        return;
+    }
+    let (x, y, padding) = (x as usize, y as usize, padding as usize);
+    let stride = target.width() as usize * 4;
+    let row_len = (width + padding * 2) * 4;
+    let target_row = |row: usize| row * stride + (x - padding) * 4;
+    let target: &mut [u8] = target;
+
+    // The interior rows, each with its first and last pixels extruded left and right
+    for (src_y, src_row) in src.chunks_exact(width * 4).enumerate() {
+        let start = target_row(y + src_y);
+        let row = &mut target[start..start + row_len];
+        let (left, rest) = row.split_at_mut(padding * 4);
+        let (interior, right) = rest.split_at_mut(width * 4);
+        interior.copy_from_slice(src_row);
+        for pixel in left.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&src_row[..4]);
+        }
+        for pixel in right.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&src_row[src_row.len() - 4..]);
         }
     }
 
-    Ok(ImageCollection::new(images))
+    // Then the first and last rows, corners included, extruded up and down
+    for i in 1..=padding {
+        let first = target_row(y);
+        target.copy_within(first..first + row_len, target_row(y - i));
+        let last = target_row(y + height - 1);
+        target.copy_within(last..last + row_len, target_row(y + height - 1 + i));
+    }
 }
 
-fn pack(
-    output_file_name: &str,
-    output_file_format: MetaDataFormat,
+fn pack(padding: u8, img_collection: ImageCollection) -> Result<Atlas, CorralError> {
+    let mut tree = Tree2d::new();
+    place(&mut tree, padding, &img_collection.named_images)?;
+    Ok(draw(&tree, padding))
+}
+
+/// Inserts `images` into `tree`, around whatever it already holds
+fn place<'a>(
+    tree: &mut Tree2d<&'a NamedDynamicImage>,
     padding: u8,
-    img_collection: ImageCollection,
-) -> Result<PackedImage, Box<dyn Error>> {
+    images: &'a [NamedDynamicImage],
+) -> Result<(), CorralError> {
     let mut data = vec![];
-    for named_img in img_collection.named_images.iter() {
+    for named_img in images {
         data.push((
             DataSize {
                 width: named_img.img.width() + padding as u32 * 2,
@@ -177,10 +574,22 @@ fn pack(
             named_img,
         ));
     }
-    let mut tree = Tree2d::<&NamedDynamicImage>::new();
-    tree.insert_all(data)?;
+    let already_placed = tree.flatten().len();
+    if let Err(source) = tree.insert_all(data) {
+        // insert_all stops at the first image that doesn't fit, after placing all those before it
+        let failed = &images[tree.flatten().len() - already_placed];
+        return Err(CorralError::Pack {
+            sprite: failed.name.clone(),
+            source,
+        });
+    }
+    Ok(())
+}
+
+/// Draws every image in `tree` into a sheet just large enough to hold them
+fn draw(tree: &Tree2d<&NamedDynamicImage>, padding: u8) -> Atlas {
     let flattened = tree.flatten();
-    let bb = tree.get_total_bounding_box();
+    let bb = tree.bounds();
     let mut img_packed =
         image::RgbaImage::new(bb.width + padding as u32, bb.height + padding as u32);
     let mut sprite_data = vec![];
@@ -191,64 +600,41 @@ fn pack(
         let width = named_img.img.width();
         let height = named_img.img.height();
 
-        // Copy pixels, extrude padding by clamping to edges
-        let start = -(padding as i64);
-        let end_y = height as i64 + padding as i64;
-        let end_x = width as i64 + padding as i64;
-        for y in start..end_y {
-            for x in start..end_x {
-                let cx = x.clamp(0, (width - 1) as i64) as u32;
-                let cy = y.clamp(0, (height - 1) as i64) as u32;
-                let p = named_img.img.get_pixel(cx, cy);
-                let tx = (target_x as i64 + x).clamp(0, (img_packed.width() - 1) as i64) as u32;
-                let ty = (target_y as i64 + y).clamp(0, (img_packed.height() - 1) as i64) as u32;
-                img_packed.put_pixel(tx, ty, p);
-            }
+        match named_img.img.as_rgba8() {
+            Some(src) => blit_extruded(&mut img_packed, src, target_x, target_y, padding as u32),
+            None => blit_extruded(
+                &mut img_packed,
+                &named_img.img.to_rgba8(),
+                target_x,
+                target_y,
+                padding as u32,
+            ),
         }
 
         let sd = SpriteData {
             name: named_img.name.to_owned(),
-            x: target_x as u32,
-            y: target_y as u32,
+            x: target_x,
+            y: target_y,
             width,
             height,
+            nine_patch: named_img.nine_patch,
         };
         sprite_data.push(sd);
     }
 
-    sprite_data.sort_by(|a, b| a.name.partial_cmp(&b.name).unwrap());
+    sprite_data.sort_by(|a, b| a.name.cmp(&b.name));
 
-    let meta_data = match output_file_format {
-        MetaDataFormat::Json => {
-            let json_string: String = sprite_data
-                .iter()
-                .map(|sd| sd.to_json_string())
-                .collect::<Vec<String>>()
-                .join(",");
-            "[".to_owned() + &json_string + "]\n"
-        }
-        MetaDataFormat::Lua => {
-            let module_name = output_file_name.split('.').collect::<Vec<&str>>()[0].to_owned();
-            let lua_string: String = sprite_data
-                .iter()
-                .map(|sd| sd.to_lua_string())
-                .collect::<Vec<String>>()
-                .join(",\n");
-            format!("local {fname} = {{\n", fname = module_name)
-                + &lua_string
-                + &format!("\n}}\n\nreturn {fname}\n", fname = module_name)
-        }
-    };
-
-    Ok(PackedImage {
-        img: DynamicImage::ImageRgba8(img_packed),
-        meta_data,
-    })
+    Atlas {
+        image: img_packed,
+        sprites: sprite_data,
+        padding,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::error::Error;
 
     fn make_rect(w: u32, h: u32) -> image::DynamicImage {
         let mut img = image::RgbaImage::new(w, h);
@@ -264,12 +650,12 @@ mod tests {
     fn pack_one() -> Result<(), Box<dyn Error>> {
         let (w, h) = (1, 1);
         let padding = 1;
-        let mut expected_output_img = image::RgbaImage::new(w + padding * 2, h + padding * 2);
-        for i in 0..w + padding * 2 {
-            for j in 0..h + padding * 2 {
-                let color = if i < padding || j < padding {
-                    image::Rgba([0, 0, 0, 0])
-                } else if i >= padding + w || j >= padding + h {
+        // The sprite is extruded into its padding, and the sheet keeps one more
+        // padding strip on the right and bottom edges
+        let mut expected_output_img = image::RgbaImage::new(w + padding * 3, h + padding * 3);
+        for i in 0..w + padding * 3 {
+            for j in 0..h + padding * 3 {
+                let color = if i >= w + padding * 2 || j >= h + padding * 2 {
                     image::Rgba([0, 0, 0, 0])
                 } else {
                     image::Rgba([255, 0, 0, 255])
@@ -281,24 +667,189 @@ mod tests {
         let img_collection = ImageCollection::new(vec![NamedDynamicImage {
             name: "red_pixel".to_owned(),
             img: make_rect(w, h),
+            nine_patch: None,
         }]);
 
-        if let Some(img) = pack(
-            &"out.png",
-            MetaDataFormat::Json,
-            padding as u8,
-            img_collection,
-        )?
-        .img
-        .as_rgba8()
-        {
-            let p: Vec<&image::Rgba<u8>> = img.pixels().collect();
-            let q: Vec<&image::Rgba<u8>> = expected_output_img.pixels().collect();
-            assert_eq!(q, p);
+        let img = pack(padding as u8, img_collection)?.image;
+        let p: Vec<&image::Rgba<u8>> = img.pixels().collect();
+        let q: Vec<&image::Rgba<u8>> = expected_output_img.pixels().collect();
+        assert_eq!(q, p);
+        Ok(())
+    }
+
+    #[test]
+    fn extrude_edges() -> Result<(), Box<dyn Error>> {
+        // A 2x2 sprite of four colours, extruded by 2 pixels, matches clamping each pixel
+        let colours = [
+            image::Rgba([255, 0, 0, 255]),
+            image::Rgba([0, 255, 0, 255]),
+            image::Rgba([0, 0, 255, 255]),
+            image::Rgba([255, 255, 0, 128]),
+        ];
+        let src = image::RgbaImage::from_fn(2, 2, |x, y| colours[(y * 2 + x) as usize]);
+        let mut target = image::RgbaImage::new(8, 8);
+        blit_extruded(&mut target, &src, 3, 2, 2);
+        for (x, y, pixel) in target.enumerate_pixels() {
+            let expected = if (1..7).contains(&x) && (0..6).contains(&y) {
+                *src.get_pixel(
+                    (x as i32 - 3).clamp(0, 1) as u32,
+                    (y as i32 - 2).clamp(0, 1) as u32,
+                )
+            } else {
+                image::Rgba([0, 0, 0, 0])
+            };
+            assert_eq!(expected, *pixel, "{x}, {y}");
         }
         Ok(())
     }
 
+    #[test]
+    fn atlas_builder() -> Result<(), Box<dyn Error>> {
+        let atlas = AtlasBuilder::new()
+            .padding(1)
+            .add_image("small", make_rect(2, 2))
+            .add_image("big", make_rect(4, 4))
+            .build()?;
+        let names: Vec<&str> = atlas.sprites.iter().map(|sd| sd.name.as_str()).collect();
+        assert_eq!(vec!["big", "small"], names);
+        for sd in &atlas.sprites {
+            assert_eq!(
+                image::Rgba([255, 0, 0, 255]),
+                *atlas.image.get_pixel(sd.x, sd.y)
+            );
+        }
+        let width = atlas.image.width();
+        let sheet_data = atlas.into_sheet_data("out/sheet.png");
+        assert_eq!(width, sheet_data.width);
+        assert_eq!(1, sheet_data.padding);
+        Ok(())
+    }
+
+    fn coloured_rect(w: u32, h: u32, colour: [u8; 4]) -> image::RgbaImage {
+        image::RgbaImage::from_pixel(w, h, image::Rgba(colour))
+    }
+
+    #[test]
+    fn redraw_from_cache() -> Result<(), Box<dyn Error>> {
+        let dir = std::env::temp_dir().join("corral-redraw-from-cache");
+        let _ = fs::remove_dir_all(&dir);
+        let input_dir = dir.join("in");
+        fs::create_dir_all(&input_dir)?;
+        coloured_rect(4, 6, [255, 0, 0, 255]).save(input_dir.join("a.png"))?;
+        coloured_rect(3, 3, [0, 255, 0, 255]).save(input_dir.join("b.png"))?;
+        coloured_rect(5, 2, [0, 0, 255, 255]).save(input_dir.join("c.png"))?;
+
+        let config = |output: &str, cache| Config {
+            padding: 2,
+            input_dir: input_dir.to_str().unwrap().to_owned(),
+            output_file: dir.join(output).to_str().unwrap().to_owned(),
+            meta_data_outputs: vec![],
+            meta_data_options: Default::default(),
+            cache,
+            stable: false,
+            watch: false,
+        };
+        let sprites = |config: &Config| {
+            let cache = Cache::load(&Cache::path(&config.output_file)).expect("cache is written");
+            let mut sprites: Vec<_> = cache
+                .sprites
+                .iter()
+                .map(|sprite| (sprite.name.clone(), sprite.x, sprite.y))
+                .collect();
+            sprites.sort();
+            sprites
+        };
+
+        run(config("sheet.png", true))?;
+        let before = sprites(&config("sheet.png", true));
+
+        // Same size, new pixels: redrawn in place, matching a packing from scratch
+        coloured_rect(3, 3, [255, 255, 0, 255]).save(input_dir.join("b.png"))?;
+        run(config("sheet.png", true))?;
+        assert_eq!(before, sprites(&config("sheet.png", true)));
+        run(config("fresh.png", false))?;
+        assert!(!Path::new(&Cache::path(&config("fresh.png", false).output_file)).exists());
+        assert_eq!(
+            image::open(dir.join("fresh.png"))?.to_rgba8(),
+            image::open(dir.join("sheet.png"))?.to_rgba8()
+        );
+
+        // A new size needs a full packing, which still succeeds
+        coloured_rect(9, 9, [255, 0, 255, 255]).save(input_dir.join("b.png"))?;
+        run(config("sheet.png", true))?;
+        let after = sprites(&config("sheet.png", true));
+        let b = after
+            .iter()
+            .find(|(name, ..)| name.ends_with("/b"))
+            .unwrap();
+        let sheet = image::open(dir.join("sheet.png"))?.to_rgba8();
+        assert_eq!(image::Rgba([255, 0, 255, 255]), *sheet.get_pixel(b.1, b.2));
+        Ok(())
+    }
+
+    #[test]
+    fn stable_layout() -> Result<(), Box<dyn Error>> {
+        let dir = std::env::temp_dir().join("corral-stable-layout");
+        let _ = fs::remove_dir_all(&dir);
+        let input_dir = dir.join("in");
+        fs::create_dir_all(&input_dir)?;
+        coloured_rect(4, 6, [255, 0, 0, 255]).save(input_dir.join("a.png"))?;
+        coloured_rect(3, 3, [0, 255, 0, 255]).save(input_dir.join("b.png"))?;
+        coloured_rect(5, 2, [0, 0, 255, 255]).save(input_dir.join("c.png"))?;
+
+        let config = |stable| Config {
+            padding: 2,
+            input_dir: input_dir.to_str().unwrap().to_owned(),
+            output_file: dir.join("sheet.png").to_str().unwrap().to_owned(),
+            meta_data_outputs: vec![],
+            meta_data_options: Default::default(),
+            cache: true,
+            stable,
+            watch: false,
+        };
+        let position = |name: &str| {
+            let cache = Cache::load(&Cache::path(&config(true).output_file)).unwrap();
+            let sprite = cache.sprites.iter().find(|s| s.name.ends_with(name));
+            sprite.map(|sprite| (sprite.x, sprite.y))
+        };
+
+        assert!(matches!(run(config(true))?, Packing::Repacked { .. }));
+        let (a, b) = (position("/a"), position("/b"));
+
+        // One sprite removed and a larger one added, which packing from scratch would put first
+        fs::remove_file(input_dir.join("c.png"))?;
+        coloured_rect(12, 12, [255, 255, 0, 255]).save(input_dir.join("d.png"))?;
+        let Packing::Stable { added, resized } = run(config(true))? else {
+            panic!("previous positions should be kept");
+        };
+        assert_eq!(1, added.len());
+        assert!(added[0].ends_with("/d"));
+        assert!(resized.is_empty());
+        assert_eq!((a, b), (position("/a"), position("/b")));
+        assert_eq!(None, position("/c"));
+
+        let sheet = image::open(dir.join("sheet.png"))?.to_rgba8();
+        let (x, y) = position("/d").unwrap();
+        assert_eq!(image::Rgba([255, 255, 0, 255]), *sheet.get_pixel(x, y));
+        let (x, y) = a.unwrap();
+        assert_eq!(image::Rgba([255, 0, 0, 255]), *sheet.get_pixel(x, y));
+
+        // A resized sprite is placed again, and told apart from new ones
+        coloured_rect(3, 4, [0, 255, 0, 255]).save(input_dir.join("b.png"))?;
+        let Packing::Stable { added, resized } = run(config(true))? else {
+            panic!("previous positions should be kept");
+        };
+        assert!(added.is_empty());
+        assert_eq!(1, resized.len());
+        assert!(resized[0].ends_with("/b"));
+        assert_eq!(a, position("/a"));
+
+        // Without --stable, the same change is packed from scratch
+        coloured_rect(2, 2, [255, 255, 255, 255]).save(input_dir.join("e.png"))?;
+        assert_eq!(Packing::Full, run(config(false))?);
+        Ok(())
+    }
+
     // #[test]
     // fn pack_many() -> Result<(), Box<dyn Error>> {
     //     let dims = vec![
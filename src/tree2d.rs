@@ -1,22 +1,73 @@
+//! Rectangle packing, with nothing specific to images
+//!
+//! Anything with a size can be packed: sprites, lightmap charts, font glyphs or UI panels.
+
+use std::collections::BTreeSet;
 use std::error::Error;
+use std::ops::Bound;
 
 use crate::bounding_box::BoundingBox;
 
-#[derive(Clone, Debug)]
-pub struct InsertionError {
-    msg: String,
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PackError {
+    /// No free space left is large enough for a rectangle of this size
+    NoSpace { width: u32, height: u32 },
+    /// A rectangle can't be kept where it was given, it overlaps another or no straight cut
+    /// separates the two
+    CannotSeed {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
 }
 
-impl Error for InsertionError {
-    fn description(&self) -> &str {
-        "error inserting data, not enough space"
+impl Error for PackError {}
+
+impl std::fmt::Display for PackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            PackError::NoSpace { width, height } => write!(
+                f,
+                "Error inserting data, no partition large enough for {width}x{height}"
+            ),
+            PackError::CannotSeed {
+                x,
+                y,
+                width,
+                height,
+            } => write!(
+                f,
+                "Error seeding data, {width}x{height} at {x}, {y} can't be kept apart from the rest"
+            ),
+        }
     }
 }
 
-impl std::fmt::Display for InsertionError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-        write!(f, "{}", self.msg)
+/// Places rectangles, each carrying some data, so that none of them overlap
+///
+/// Implementations keep to this contract:
+/// - placed rectangles have exactly the size they were inserted with, and never overlap
+/// - once placed, a rectangle never moves, so positions can be used as soon as `insert` returns
+/// - a failed insert leaves the packer as it was, and can be followed by further inserts
+/// - `insert_all` inserts in the order given and stops at the first failure, keeping the
+///   rectangles placed before it
+/// - `flatten` returns every placed rectangle once, in no particular order
+/// - `bounds` is the smallest box at the origin containing every placed rectangle, with
+///   zero width and height when nothing has been placed
+pub trait Packer<T> {
+    fn insert(&mut self, width: u32, height: u32, data: T) -> Result<(), PackError>;
+
+    fn insert_all(&mut self, data: Vec<(DataSize, T)>) -> Result<(), PackError> {
+        for (DataSize { width, height }, data) in data {
+            self.insert(width, height, data)?;
+        }
+        Ok(())
     }
+
+    fn flatten(&self) -> Vec<(&T, BoundingBox)>;
+
+    fn bounds(&self) -> BoundingBox;
 }
 
 type Handle = usize;
@@ -59,16 +110,114 @@ impl<T> Arena<T> {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct DataSize {
     pub width: u32,
     pub height: u32,
 }
 
+/// Packs by splitting free space into two each time a rectangle is placed, choosing the
+/// space that keeps the packed area closest to square
 #[allow(dead_code)]
 pub struct Tree2d<T> {
     root: Handle,
     nodes: Arena<Node<T>>,
+    free: FreeLeaves,
+    bounds: BoundingBox,
+}
+
+/// The leaves of a tree, indexed so an insert only compares a handful of them
+///
+/// Leaves closed in on every side lie within the packed area, so any of them takes a rectangle
+/// without growing it, and the best fit is found with a range query over their sizes. The
+/// columns open at the bottom and the rows open at the right are where the packed area grows.
+/// How square it stays only gets worse the further a rectangle lands from the packed area's
+/// longer side, so only the nearest column and row on either side of that are compared. Each
+/// query skips the leaves too small to take the rectangle, and leaves with no area are left
+/// out, nothing needing one would fit only there.
+#[derive(Default)]
+struct FreeLeaves {
+    /// Width, height
+    enclosed: BTreeSet<(u32, u32, Handle)>,
+    /// Top, width
+    columns: BTreeSet<(u32, u32, Handle)>,
+    /// Left, height
+    rows: BTreeSet<(u32, u32, Handle)>,
+    /// Open at both the right and bottom, the space beyond everything placed
+    corners: BTreeSet<Handle>,
+}
+
+impl FreeLeaves {
+    fn insert(&mut self, handle: Handle, bb: BoundingBox) {
+        if bb.width == 0 || bb.height == 0 {
+            return;
+        }
+        match (u32::MAX - bb.width == bb.x, u32::MAX - bb.height == bb.y) {
+            (false, false) => self.enclosed.insert((bb.width, bb.height, handle)),
+            (false, true) => self.columns.insert((bb.y, bb.width, handle)),
+            (true, false) => self.rows.insert((bb.x, bb.height, handle)),
+            (true, true) => self.corners.insert(handle),
+        };
+    }
+
+    fn remove(&mut self, handle: Handle, bb: BoundingBox) {
+        match (u32::MAX - bb.width == bb.x, u32::MAX - bb.height == bb.y) {
+            (false, false) => self.enclosed.remove(&(bb.width, bb.height, handle)),
+            (false, true) => self.columns.remove(&(bb.y, bb.width, handle)),
+            (true, false) => self.rows.remove(&(bb.x, bb.height, handle)),
+            (true, true) => self.corners.remove(&handle),
+        };
+    }
+
+    /// Leaves worth comparing for a rectangle of this size, when the packed area's longer side
+    /// is `longest`: the narrowest enclosed leaf it fits, then the shortest, then the columns
+    /// and rows nearest to reaching `longest`, then the corners
+    fn candidates(&self, width: u32, height: u32, longest: u32) -> Vec<Handle> {
+        let mut candidates = vec![];
+        candidates.extend(
+            self.enclosed
+                .range((width, height, 0)..)
+                .find(|(_, leaf_height, _)| *leaf_height >= height)
+                .map(|(_, _, handle)| *handle),
+        );
+        candidates.extend(nearest(
+            &self.columns,
+            longest.saturating_sub(height),
+            width,
+        ));
+        candidates.extend(nearest(&self.rows, longest.saturating_sub(width), height));
+        candidates.extend(self.corners.iter().copied());
+        candidates
+    }
+
+    #[cfg(test)]
+    fn all(&self) -> impl Iterator<Item = Handle> + '_ {
+        self.enclosed
+            .iter()
+            .chain(&self.columns)
+            .chain(&self.rows)
+            .map(|(_, _, handle)| *handle)
+            .chain(self.corners.iter().copied())
+    }
+}
+
+/// The last leaf at or before `at` and the first after it, of those at least `size` across
+fn nearest(
+    leaves: &BTreeSet<(u32, u32, Handle)>,
+    at: u32,
+    size: u32,
+) -> impl Iterator<Item = Handle> + '_ {
+    let split = (at, u32::MAX, Handle::MAX);
+    let fits = |(_, leaf_size, _): &&(u32, u32, Handle)| *leaf_size >= size;
+    [
+        leaves.range(..=split).rev().find(fits),
+        leaves
+            .range((Bound::Excluded(split), Bound::Unbounded))
+            .find(fits),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(_, _, handle)| *handle)
 }
 
 #[allow(dead_code)]
@@ -80,7 +229,8 @@ struct Node<T> {
 
 #[allow(dead_code)]
 struct Link<T> {
-    data: T,
+    /// `None` when the node was only split in two, leaving both halves free
+    data: Option<T>,
     data_bb: BoundingBox,
     down: Handle,
     right: Handle,
@@ -95,6 +245,7 @@ impl<T> Node<T> {
         }
     }
 
+    #[cfg(test)]
     fn is_leaf(&self) -> bool {
         match self.link {
             None => true,
@@ -103,6 +254,12 @@ impl<T> Node<T> {
     }
 }
 
+impl<T> Default for Tree2d<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Tree2d<T> {
     pub fn new() -> Self {
         let node: Node<T> = Node::new_leaf(
@@ -115,120 +272,88 @@ impl<T> Tree2d<T> {
             None,
         );
 
+        let bb = node.bb;
         let mut nodes = Arena::new();
         let root = nodes.store(node);
 
-        Tree2d { root, nodes }
-    }
-
-    pub fn get_total_bounding_box(&self) -> BoundingBox {
-        let mut result = BoundingBox {
-            x: 0,
-            y: 0,
-            width: 0,
-            height: 0,
-        };
+        let mut free = FreeLeaves::default();
+        free.insert(root, bb);
 
-        for node in self.nodes.data.iter() {
-            if let Some(link) = &node.link {
-                result = result + link.data_bb
-            }
-        }
-
-        result
-    }
-
-    #[allow(dead_code)]
-    pub fn insert(&mut self, width: u32, height: u32, data: T) -> Result<(), Box<dyn Error>> {
-        let total_bb = self.get_total_bounding_box();
-        let handle = self.get_most_square_leaf_handle_for_data(total_bb, width, height);
-        match handle {
-            None => Err(Box::new(InsertionError {
-                msg: "Error inserting data, no partition large enough".to_owned(),
-            })),
-            Some((handle, _)) => {
-                self.partition(handle, data, width, height);
-                Ok(())
-            }
+        Tree2d {
+            root,
+            nodes,
+            free,
+            bounds: BoundingBox {
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 0,
+            },
         }
     }
 
-    pub fn insert_all(&mut self, data: Vec<(DataSize, T)>) -> Result<(), Box<dyn Error>> {
-        // self.nodes.data.reserve(data.len() * 2);
-        let mut total_bb = self.get_total_bounding_box();
-        for (DataSize { width, height }, data) in data {
-            let handle = self.get_most_square_leaf_handle_for_data(total_bb, width, height);
-            match handle {
-                None => Err(Box::new(InsertionError {
-                    msg: "Error inserting data, no partition large enough".to_owned(),
-                })),
-                Some((handle, bb)) => {
-                    self.partition(handle, data, width, height);
-                    total_bb = bb;
-                    Ok(())
+    /// A tree with rectangles already placed where given, for carrying a layout over from an
+    /// earlier packing. Further inserts go in the space left around them.
+    ///
+    /// The rectangles must be separable by straight cuts, then by cuts across each side, and so
+    /// on, which every layout packed by a `Tree2d` is. Fails with `PackError::CannotSeed`
+    /// otherwise, including when rectangles overlap.
+    pub fn seeded(placed: Vec<(BoundingBox, T)>) -> Result<Self, PackError> {
+        let mut tree = Self::new();
+        let mut pending = vec![(tree.root, placed)];
+        while let Some((handle, mut placed)) = pending.pop() {
+            let bb = match tree.nodes.get(handle) {
+                Some(node) => node.bb,
+                None => continue,
+            };
+            if let [(data_bb, _)] = placed.as_slice() {
+                if (data_bb.x, data_bb.y) == (bb.x, bb.y) {
+                    if !bb.can_contain(data_bb.width, data_bb.height) {
+                        return Err(cannot_seed(data_bb));
+                    }
+                    let (data_bb, data) = placed.remove(0);
+                    tree.partition(handle, data, data_bb.width, data_bb.height);
+                    continue;
                 }
-            }?;
+            }
+            if placed.is_empty() {
+                continue;
+            }
+            let (first_bb, second_bb) = match cut(bb, &placed) {
+                Some(halves) => halves,
+                None => return Err(cannot_seed(&placed[0].0)),
+            };
+            // Nothing straddles the cut, so whichever side a rectangle starts on, it's all there
+            let (first_placed, second_placed) = placed
+                .into_iter()
+                .partition(|(data_bb, _)| data_bb.x < second_bb.x || data_bb.y < second_bb.y);
+            let (first, second) = tree.split(handle, first_bb, second_bb);
+            pending.push((first, first_placed));
+            pending.push((second, second_placed));
         }
-        Ok(())
+        Ok(tree)
     }
 
-    pub fn flatten(&self) -> Vec<(&T, BoundingBox)> {
-        let mut result = vec![];
-        for node in self.nodes.data.iter() {
-            match &node.link {
-                None => (),
-                Some(link) => result.push((&link.data, link.data_bb)),
-            };
-        }
-        result
+    pub fn get_total_bounding_box(&self) -> BoundingBox {
+        self.bounds
     }
 
+    #[cfg(test)]
     fn leaves(&self) -> Vec<Handle> {
-        let mut result = vec![];
-        for (i, node) in self.nodes.data.iter().enumerate() {
-            if node.is_leaf() {
-                result.push(i);
-            }
-        }
+        let mut result: Vec<Handle> = self.free.all().collect();
+        result.sort_unstable();
         result
     }
 
-    #[allow(dead_code)]
-    fn get_smallest_leaf_handle_for_data(&mut self, width: u32, height: u32) -> Option<Handle> {
-        let mut leaves = vec![];
-        let total_bb = self.get_total_bounding_box();
-        for handle in self.leaves() {
-            if let Some(node) = self.nodes.get(handle) {
-                if node.bb.can_contain(width, height) {
-                    let bb = total_bb
-                        + BoundingBox {
-                            x: node.bb.x,
-                            y: node.bb.y,
-                            width,
-                            height,
-                        };
-
-                    leaves.push((bb, handle));
-                }
-            }
-        }
-        if leaves.is_empty() {
-            None
-        } else {
-            leaves.sort_by(|a, b| a.0.cmp(&b.0));
-            Some(leaves[0].1)
-        }
-    }
-
     fn get_most_square_leaf_handle_for_data(
         &mut self,
         total_bb: BoundingBox,
         width: u32,
         height: u32,
     ) -> Option<(Handle, BoundingBox)> {
-        let mut leaves = vec![];
-        // let total_bb = self.get_total_bounding_box();
-        for handle in self.leaves() {
+        let longest = total_bb.width.max(total_bb.height);
+        let mut best: Option<(f64, Handle, BoundingBox)> = None;
+        for handle in self.free.candidates(width, height, longest) {
             if let Some(node) = self.nodes.get(handle) {
                 if node.bb.can_contain(width, height) {
                     let bb = total_bb
@@ -240,16 +365,42 @@ impl<T> Tree2d<T> {
                         };
                     let ratio = (bb.width as f64 / bb.height as f64)
                         .max(bb.height as f64 / bb.width as f64);
-                    leaves.push((ratio, handle, bb));
+                    // Ties go to the earlier candidate, filling enclosed space before growing
+                    if best.is_none_or(|(best_ratio, _, _)| ratio < best_ratio) {
+                        best = Some((ratio, handle, bb));
+                    }
                 }
             }
         }
-        if leaves.is_empty() {
-            None
-        } else {
-            leaves.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-            Some((leaves[0].1, leaves[0].2))
+        best.map(|(_, handle, bb)| (handle, bb))
+    }
+
+    /// Turns a leaf into two, `first` and `second` covering it between them, both left free
+    fn split(
+        &mut self,
+        handle: Handle,
+        first: BoundingBox,
+        second: BoundingBox,
+    ) -> (Handle, Handle) {
+        let first_handle = self.nodes.store(Node::new_leaf(first, Some(handle)));
+        let second_handle = self.nodes.store(Node::new_leaf(second, Some(handle)));
+        self.free.insert(first_handle, first);
+        self.free.insert(second_handle, second);
+        if let Some(node) = self.nodes.get_mut(handle) {
+            self.free.remove(handle, node.bb);
+            node.link = Some(Link {
+                data: None,
+                data_bb: BoundingBox {
+                    x: node.bb.x,
+                    y: node.bb.y,
+                    width: 0,
+                    height: 0,
+                },
+                down: second_handle,
+                right: first_handle,
+            });
         }
+        (first_handle, second_handle)
     }
 
     fn partition(&mut self, handle: Handle, data: T, width: u32, height: u32) {
@@ -312,8 +463,11 @@ impl<T> Tree2d<T> {
         };
 
         if let (Some(right), Some(down)) = (right, down) {
+            let (right_bb, down_bb) = (right.bb, down.bb);
             let right_handle = self.nodes.store(right);
             let down_handle = self.nodes.store(down);
+            self.free.insert(right_handle, right_bb);
+            self.free.insert(down_handle, down_bb);
             if let Some(node) = self.nodes.get_mut(handle) {
                 let data_bb = BoundingBox {
                     x: node.bb.x,
@@ -321,11 +475,13 @@ impl<T> Tree2d<T> {
                     width,
                     height,
                 };
+                self.free.remove(handle, node.bb);
+                self.bounds = self.bounds + data_bb;
                 *node = Node {
                     bb: node.bb,
                     parent: None,
                     link: Some(Link {
-                        data,
+                        data: Some(data),
                         data_bb,
                         down: down_handle,
                         right: right_handle,
@@ -336,6 +492,118 @@ impl<T> Tree2d<T> {
     }
 }
 
+fn cannot_seed(bb: &BoundingBox) -> PackError {
+    PackError::CannotSeed {
+        x: bb.x,
+        y: bb.y,
+        width: bb.width,
+        height: bb.height,
+    }
+}
+
+/// The first position after `start` that no span straddles and at least one span starts at
+fn gap(start: u32, spans: impl Iterator<Item = (u32, u32)>) -> Option<u32> {
+    let mut spans: Vec<(u32, u32)> = spans.collect();
+    spans.sort_unstable();
+    let mut reach = start;
+    for (from, to) in spans {
+        if from >= reach && from > start {
+            return Some(from);
+        }
+        reach = reach.max(to);
+    }
+    None
+}
+
+/// Splits `bb` in two across one axis, without cutting through any placed rectangle
+///
+/// Each cut is at the edge of some rectangle, so repeating it on either half eventually leaves
+/// every rectangle alone at the top left of its own part.
+fn cut<T>(bb: BoundingBox, placed: &[(BoundingBox, T)]) -> Option<(BoundingBox, BoundingBox)> {
+    let rows = placed
+        .iter()
+        .map(|(p, _)| (p.y, p.y.saturating_add(p.height)));
+    if let Some(y) = gap(bb.y, rows) {
+        return Some((
+            BoundingBox {
+                height: y - bb.y,
+                ..bb
+            },
+            BoundingBox {
+                y,
+                height: bb.height - (y - bb.y),
+                ..bb
+            },
+        ));
+    }
+    let columns = placed
+        .iter()
+        .map(|(p, _)| (p.x, p.x.saturating_add(p.width)));
+    gap(bb.x, columns).map(|x| {
+        (
+            BoundingBox {
+                width: x - bb.x,
+                ..bb
+            },
+            BoundingBox {
+                x,
+                width: bb.width - (x - bb.x),
+                ..bb
+            },
+        )
+    })
+}
+
+impl<T> Packer<T> for Tree2d<T> {
+    fn insert(&mut self, width: u32, height: u32, data: T) -> Result<(), PackError> {
+        let total_bb = self.get_total_bounding_box();
+        let handle = self.get_most_square_leaf_handle_for_data(total_bb, width, height);
+        match handle {
+            None => Err(PackError::NoSpace { width, height }),
+            Some((handle, _)) => {
+                self.partition(handle, data, width, height);
+                Ok(())
+            }
+        }
+    }
+
+    fn insert_all(&mut self, data: Vec<(DataSize, T)>) -> Result<(), PackError> {
+        // self.nodes.data.reserve(data.len() * 2);
+        let mut total_bb = self.get_total_bounding_box();
+        for (DataSize { width, height }, data) in data {
+            let handle = self.get_most_square_leaf_handle_for_data(total_bb, width, height);
+            match handle {
+                None => Err(PackError::NoSpace { width, height }),
+                Some((handle, bb)) => {
+                    self.partition(handle, data, width, height);
+                    total_bb = bb;
+                    Ok(())
+                }
+            }?;
+        }
+        Ok(())
+    }
+
+    fn flatten(&self) -> Vec<(&T, BoundingBox)> {
+        let mut result = vec![];
+        for node in self.nodes.data.iter() {
+            if let Some(Link {
+                data: Some(data),
+                data_bb,
+                ..
+            }) = &node.link
+            {
+                result.push((data, *data_bb));
+            }
+        }
+        result
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.get_total_bounding_box()
+    }
+}
+
 #[cfg(test)]
 mod tree_2d_tests {
     use super::*;
@@ -348,7 +616,7 @@ mod tree_2d_tests {
         if let Some(node) = root_node {
             assert!(node.is_leaf(), "root in empty tree should be leaf");
         } else {
-            assert!(false, "root should be Some");
+            panic!("root should be Some");
         }
 
         assert_eq!(
@@ -417,29 +685,6 @@ mod tree_2d_tests {
         Ok(())
     }
 
-    #[test]
-    fn get_smallest_leaf_for_data() -> Result<(), Box<dyn Error>> {
-        let mut tree = Tree2d::<u32>::new();
-        let data = 1u32;
-
-        let width = 2;
-        let height = 1;
-
-        assert_eq!(
-            tree.get_smallest_leaf_handle_for_data(width, height),
-            Some(0)
-        );
-
-        tree.partition(tree.root, data, width, height);
-
-        assert_eq!(
-            tree.get_smallest_leaf_handle_for_data(width, height),
-            Some(2)
-        );
-
-        Ok(())
-    }
-
     #[test]
     fn insert() -> Result<(), Box<dyn Error>> {
         let mut tree = Tree2d::<u32>::new();
@@ -481,20 +726,171 @@ mod tree_2d_tests {
         Ok(())
     }
 
-    // #[test]
-    // fn one_million_insertions() -> Result<(), Box<dyn Error>> {
-    //     let mut tree = Tree2d::<u32>::new();
-    //     let data = vec![
-    //         (
-    //             DataSize {
-    //                 width: 1,
-    //                 height: 1
-    //             },
-    //             0
-    //         );
-    //         100_000
-    //     ];
-    //     tree.insert_all(data)?;
-    //     Ok(())
-    // }
+    #[test]
+    fn no_space() -> Result<(), Box<dyn Error>> {
+        let mut tree = Tree2d::<u32>::new();
+        tree.insert(u32::MAX, u32::MAX, 1)?;
+        assert_eq!(
+            Err(PackError::NoSpace {
+                width: 1,
+                height: 1
+            }),
+            tree.insert(1, 1, 2)
+        );
+        assert_eq!(1, tree.flatten().len());
+        Ok(())
+    }
+
+    #[test]
+    fn packer_contract() -> Result<(), Box<dyn Error>> {
+        fn pack_glyphs(packer: &mut impl Packer<char>) -> Result<(), PackError> {
+            packer.insert_all(
+                "corral"
+                    .chars()
+                    .enumerate()
+                    .map(|(i, c)| {
+                        (
+                            DataSize {
+                                width: 3 + i as u32,
+                                height: 8 - i as u32,
+                            },
+                            c,
+                        )
+                    })
+                    .collect(),
+            )
+        }
+
+        let mut tree = Tree2d::new();
+        pack_glyphs(&mut tree)?;
+        let placed = tree.flatten();
+        assert_eq!(6, placed.len());
+        let bounds = tree.bounds();
+        for (i, (_, bb)) in placed.iter().enumerate() {
+            assert_eq!(bounds, bounds + *bb, "{bb:?} should be inside {bounds:?}");
+            for (_, other) in &placed[i + 1..] {
+                let overlaps = bb.x < other.x + other.width
+                    && other.x < bb.x + bb.width
+                    && bb.y < other.y + other.height
+                    && other.y < bb.y + bb.height;
+                assert!(!overlaps, "{bb:?} overlaps {other:?}");
+            }
+        }
+        Ok(())
+    }
+
+    fn overlaps(bb: &BoundingBox, other: &BoundingBox) -> bool {
+        bb.x < other.x + other.width
+            && other.x < bb.x + bb.width
+            && bb.y < other.y + other.height
+            && other.y < bb.y + bb.height
+    }
+
+    #[test]
+    fn seeded() -> Result<(), Box<dyn Error>> {
+        let mut packed = Tree2d::new();
+        for i in 0..40u32 {
+            packed.insert(3 + i % 7, 2 + i % 5, i)?;
+        }
+        // Every other one kept, leaving holes where the rest were
+        let mut kept: Vec<(BoundingBox, u32)> = packed
+            .flatten()
+            .into_iter()
+            .filter(|(i, _)| *i % 2 == 0)
+            .map(|(i, bb)| (bb, *i))
+            .collect();
+        kept.sort_by_key(|(_, i)| *i);
+
+        let mut tree = Tree2d::seeded(kept.clone())?;
+        let mut placed: Vec<(BoundingBox, u32)> =
+            tree.flatten().into_iter().map(|(i, bb)| (bb, *i)).collect();
+        placed.sort_by_key(|(_, i)| *i);
+        assert_eq!(kept, placed);
+
+        for i in 40..60u32 {
+            tree.insert(3 + i % 7, 2 + i % 5, i)?;
+        }
+        let placed = tree.flatten();
+        assert_eq!(40, placed.len());
+        for (i, (_, bb)) in placed.iter().enumerate() {
+            for (_, other) in &placed[i + 1..] {
+                assert!(!overlaps(bb, other), "{bb:?} overlaps {other:?}");
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cannot_seed_overlapping() {
+        let bb = |x, y| BoundingBox {
+            x,
+            y,
+            width: 4,
+            height: 4,
+        };
+        assert!(matches!(
+            Tree2d::seeded(vec![(bb(0, 0), 1), (bb(2, 2), 2)]),
+            Err(PackError::CannotSeed { .. })
+        ));
+        // Apart, but a pinwheel that no straight cut separates
+        let pinwheel = vec![
+            (
+                BoundingBox {
+                    x: 0,
+                    y: 0,
+                    width: 2,
+                    height: 1,
+                },
+                1,
+            ),
+            (
+                BoundingBox {
+                    x: 2,
+                    y: 0,
+                    width: 1,
+                    height: 2,
+                },
+                2,
+            ),
+            (
+                BoundingBox {
+                    x: 1,
+                    y: 2,
+                    width: 2,
+                    height: 1,
+                },
+                3,
+            ),
+            (
+                BoundingBox {
+                    x: 0,
+                    y: 1,
+                    width: 1,
+                    height: 2,
+                },
+                4,
+            ),
+        ];
+        assert!(Tree2d::seeded(pinwheel).is_err());
+    }
+
+    #[test]
+    fn one_hundred_thousand_insertions() -> Result<(), Box<dyn Error>> {
+        let mut tree = Tree2d::<u32>::new();
+        let data = vec![
+            (
+                DataSize {
+                    width: 1,
+                    height: 1,
+                },
+                0
+            );
+            100_000
+        ];
+        tree.insert_all(data)?;
+        assert_eq!(100_000, tree.flatten().len());
+        let bounds = tree.bounds();
+        assert_eq!((317, 317), (bounds.width, bounds.height));
+        Ok(())
+    }
 }
@@ -0,0 +1,109 @@
+//! Packing again whenever the input directory changes, as `corral --watch` does
+
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::config::Config;
+use crate::error::CorralError;
+use crate::packer;
+
+/// How long the input directory has to stay quiet before packing, so that saving several files,
+/// or one file in several writes, packs once rather than many times
+const QUIET: Duration = Duration::from_millis(200);
+
+/// Packs once, then again after each burst of changes to `config.input_dir`, until killed
+///
+/// Errors while packing are printed and then waited out, as the next save may well fix them.
+/// Only failing to watch the directory at all ends it.
+pub fn run(config: Config) -> Result<(), CorralError> {
+    let watch_error = |err| CorralError::io(&config.input_dir, io::Error::other(err));
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(watch_error)?;
+    watcher
+        .watch(Path::new(&config.input_dir), RecursiveMode::NonRecursive)
+        .map_err(watch_error)?;
+
+    println!("Watching {} for changes", config.input_dir);
+    pack(&config);
+    while let Some(changed) = next_changes(&rx, QUIET) {
+        let changed: Vec<String> = changed
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        println!("Changed {}", changed.join(", "));
+        pack(&config);
+    }
+    Ok(())
+}
+
+fn pack(config: &Config) {
+    let start = Instant::now();
+    match packer::run(config.clone()) {
+        Ok(packing) => println!("{packing} ({elapsed:.0?})", elapsed = start.elapsed()),
+        Err(err) => eprintln!("{err}"),
+    }
+}
+
+/// Waits for files to change, then for `quiet` to pass without any more changing, returning
+/// every file changed in between. `None` once the watcher has stopped.
+fn next_changes(
+    rx: &Receiver<notify::Result<Event>>,
+    quiet: Duration,
+) -> Option<BTreeSet<PathBuf>> {
+    loop {
+        let mut changed = BTreeSet::new();
+        add_changes(rx.recv().ok()?, &mut changed);
+        while let Ok(event) = rx.recv_timeout(quiet) {
+            add_changes(event, &mut changed);
+        }
+        // A burst of files only being read, by corral among others, isn't a change
+        if !changed.is_empty() {
+            return Some(changed);
+        }
+    }
+}
+
+fn add_changes(event: notify::Result<Event>, changed: &mut BTreeSet<PathBuf>) {
+    match event {
+        Ok(Event {
+            kind: EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_),
+            paths,
+            ..
+        }) => changed.extend(paths),
+        Ok(_) => (),
+        Err(err) => eprintln!("Error watching for changes: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, ModifyKind, RemoveKind};
+
+    #[test]
+    fn debounced_changes() {
+        let (tx, rx) = mpsc::channel();
+        let event = |kind, path: &str| Ok(Event::new(kind).add_path(PathBuf::from(path)));
+        tx.send(event(EventKind::Modify(ModifyKind::Any), "in/a.png"))
+            .unwrap();
+        tx.send(event(EventKind::Access(AccessKind::Any), "in/b.png"))
+            .unwrap();
+        tx.send(event(EventKind::Create(CreateKind::File), "in/a.png"))
+            .unwrap();
+        tx.send(event(EventKind::Remove(RemoveKind::File), "in/c.png"))
+            .unwrap();
+        drop(tx);
+
+        let changed = next_changes(&rx, Duration::from_millis(10)).unwrap();
+        assert_eq!(
+            vec![PathBuf::from("in/a.png"), PathBuf::from("in/c.png")],
+            changed.into_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(None, next_changes(&rx, Duration::from_millis(10)));
+    }
+}